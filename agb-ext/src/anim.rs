@@ -1,45 +1,96 @@
-use agb::{
-  display::object::{Object, OamManaged, Tag},
-};
-use agb::fixnum::Vector2D;
+use alloc::vec::Vec;
+use agb_fixnum::Vector2D;
+use crate::hw::{Object, OamManaged, Tag};
 
 #[derive(Clone, Copy, PartialEq)]
 pub struct AnimId(pub u8);
 
+/// Markers on a frame, such as a footstep or the start of a hitbox, reported once when the frame starts. What each bit
+/// means is up to the game.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct AnimEvents(pub u16);
+
 #[derive(Clone, Copy)]
 pub struct Frame {
   pub tag: &'static Tag,
   pub tag_idx: u8,
   pub duration: u8,
+  pub events: AnimEvents,
+}
+
+/// Anything an animation can step through, each shown for `duration` frames.
+pub trait AnimFrame: Copy + 'static {
+  fn duration(&self) -> u8;
+  fn events(&self) -> AnimEvents;
 }
 
 #[derive(Clone, Copy)]
-pub struct Anim {
-  pub frames: &'static [Frame],
+pub struct Anim<F: 'static = Frame> {
+  pub frames: &'static [F],
   pub next_anim: Option<AnimId>,
 }
 
-pub struct AnimPlayer<'o> {
-  get_next_anim: fn(AnimId) -> Anim,
-  cur_anim: Anim,
+/// Steps through the frames of an animation and on to the one after it.
+struct AnimClock<F: 'static> {
+  get_next_anim: fn(AnimId) -> Anim<F>,
+  cur_anim: Anim<F>,
   cur_anim_id: AnimId,
   frame_idx: usize,
   frame_duration: u8,
+  /// Events of the frames started since they were last taken.
+  events: AnimEvents,
+}
 
+pub struct AnimPlayer<'o> {
+  clock: AnimClock<Frame>,
   sprite: Object<'o>,
 }
 
+/// One object of a metasprite frame. `offset` is from the metasprite's top left and `size` is the sprite's size, which
+/// is needed to mirror the part when the whole metasprite flips.
+#[derive(Clone, Copy)]
+pub struct SpritePart {
+  pub tag: &'static Tag,
+  pub tag_idx: u8,
+  pub offset: Vector2D<i32>,
+  pub size: Vector2D<i32>,
+  pub hflip: bool,
+  pub vflip: bool,
+}
+
+/// A frame built from several objects, for things bigger than the largest sprite. `size` is the area the parts are laid
+/// out in, which they're mirrored across when flipped.
+#[derive(Clone, Copy)]
+pub struct MetaFrame {
+  pub parts: &'static [SpritePart],
+  pub size: Vector2D<i32>,
+  pub duration: u8,
+  pub events: AnimEvents,
+}
+
+pub type MetaAnim = Anim<MetaFrame>;
+
+/// Plays a `MetaAnim`, keeping one object per part of the current frame.
+pub struct Metasprite<'o> {
+  clock: AnimClock<MetaFrame>,
+  objects: Vec<Object<'o>>,
+  position: Vector2D<i32>,
+  hflip: bool,
+  vflip: bool,
+}
+
 #[macro_export]
 macro_rules! new_anim {
   ( $tag:expr, $next_anim:expr, $( $frame_data:expr ),* ) => {
     {
-      use agb_ext::anim::Frame;
+      use agb_ext::anim::{AnimEvents, Frame};
       static frames: &[Frame] = &[
         $(
           Frame {
             tag: $tag,
             tag_idx: $frame_data.0,
             duration: $frame_data.1,
+            events: AnimEvents::NONE,
           },
         )*
       ];
@@ -53,12 +104,12 @@ macro_rules! new_anim {
 
 #[macro_export]
 macro_rules! anim_enum {
-  ( $name:ident { $($anim:tt => $id:expr),* } ) => {
+  ( $vis:vis $name:ident { $($anim:tt => $id:expr),* } ) => {
       use agb_ext::anim::AnimId;
       use core::convert::{Into, From};
 
       #[derive(Copy, Clone, PartialEq)]
-      enum $name {
+      $vis enum $name {
         $(
           $anim
         ),+
@@ -89,55 +140,136 @@ macro_rules! anim_enum {
   }
 }
 
+impl AnimEvents {
+  pub const NONE: AnimEvents = AnimEvents(0);
+
+  pub const fn bit(idx: u8) -> AnimEvents {
+    AnimEvents(1 << idx)
+  }
+
+  pub const fn union(self, other: AnimEvents) -> AnimEvents {
+    AnimEvents(self.0 | other.0)
+  }
+
+  pub const fn contains(self, other: AnimEvents) -> bool {
+    self.0 & other.0 == other.0
+  }
+
+  pub const fn is_empty(self) -> bool {
+    self.0 == 0
+  }
+}
+
+impl AnimFrame for Frame {
+  fn duration(&self) -> u8 {
+    self.duration
+  }
+
+  fn events(&self) -> AnimEvents {
+    self.events
+  }
+}
+
+impl<F: AnimFrame> AnimClock<F> {
+  fn new(get_next_anim: fn(AnimId) -> Anim<F>, first_anim_id: AnimId) -> Self {
+    let first_anim = get_next_anim(first_anim_id);
+    AnimClock {
+      get_next_anim,
+      cur_anim: first_anim,
+      cur_anim_id: first_anim_id,
+      frame_idx: 0,
+      frame_duration: first_anim.frames[0].duration(),
+      events: first_anim.frames[0].events(),
+    }
+  }
+
+  fn frame(&self) -> F {
+    self.cur_anim.frames[self.frame_idx]
+  }
+
+  /// Counts down the current frame, returning whether a new one started. An animation without a next one holds on its
+  /// last frame.
+  fn tick(&mut self) -> bool {
+    self.frame_duration = self.frame_duration.saturating_sub(1);
+    if self.frame_duration > 0 {
+      return false;
+    }
+    if self.frame_idx + 1 < self.cur_anim.frames.len() {
+      self.frame_idx += 1;
+      self.start_frame();
+      true
+    } else if let Some(next_anim_id) = self.cur_anim.next_anim {
+      self.set(next_anim_id);
+      true
+    } else {
+      false
+    }
+  }
+
+  fn set(&mut self, anim_id: AnimId) {
+    self.cur_anim_id = anim_id;
+    self.cur_anim = (self.get_next_anim)(anim_id);
+    self.frame_idx = 0;
+    self.start_frame();
+  }
+
+  fn start_frame(&mut self) {
+    let frame = self.frame();
+    self.frame_duration = frame.duration();
+    self.events = self.events.union(frame.events());
+  }
+
+  fn take_events(&mut self) -> AnimEvents {
+    core::mem::take(&mut self.events)
+  }
+}
+
+impl AnimFrame for MetaFrame {
+  fn duration(&self) -> u8 {
+    self.duration
+  }
+
+  fn events(&self) -> AnimEvents {
+    self.events
+  }
+}
+
 impl<'o> AnimPlayer<'o> {
   pub fn new(object: &'o OamManaged, get_next_anim: fn(AnimId) -> Anim, first_anim_enum: AnimId) -> AnimPlayer<'o> {
-    let first_anim = get_next_anim(first_anim_enum.clone());
-    let first_frame = first_anim.frames[0];
+    let clock = AnimClock::new(get_next_anim, first_anim_enum);
+    let first_frame = clock.frame();
     let mut sprite = object.object_sprite(first_frame.tag.sprite(first_frame.tag_idx as usize));
     sprite.show();
 
     AnimPlayer {
-      get_next_anim,
-      cur_anim: first_anim,
-      cur_anim_id: first_anim_enum,
-      frame_idx: 0,
-      frame_duration: first_frame.duration,
+      clock,
       sprite,
     }
   }
 
-  pub fn draw(&mut self, object: &'o OamManaged) {
-    self.frame_duration -= 1;
-    if self.frame_duration == 0 {
-      self.frame_idx += 1;
-      if self.frame_idx == self.cur_anim.frames.len() {
-        if let Some(next_anim_enum) = self.cur_anim.next_anim.clone() {
-          self.force_set_anim(next_anim_enum, object);
-        }
-      } else {
-        self.load_frame(object);
-      }
+  /// Advances the animation, returning the events of every frame started since the last draw, including by
+  /// `set_anim`.
+  pub fn draw(&mut self, object: &'o OamManaged) -> AnimEvents {
+    if self.clock.tick() {
+      self.load_frame(object);
     }
+    self.clock.take_events()
   }
 
   pub fn set_anim(&mut self, anim: AnimId, object: &'o OamManaged) {
-    if self.cur_anim_id != anim {
+    if self.clock.cur_anim_id != anim {
       self.force_set_anim(anim, object);
     }
   }
 
   pub fn force_set_anim(&mut self, anim: AnimId, object: &'o OamManaged) {
-    self.cur_anim_id = anim.clone();
-    let next_anim = (self.get_next_anim)(anim);
-    self.cur_anim = next_anim;
-    self.frame_idx = 0;
+    self.clock.set(anim);
     self.load_frame(object);
   }
 
   fn load_frame(&mut self, object: &'o OamManaged) {
-    let frame = self.cur_anim.frames[self.frame_idx];
+    let frame = self.clock.frame();
     self.sprite.set_sprite(object.sprite(frame.tag.sprite(frame.tag_idx as usize)));
-    self.frame_duration = frame.duration;
   }
 
   pub fn sprite(&self) -> &Object<'o> {
@@ -149,10 +281,99 @@ impl<'o> AnimPlayer<'o> {
   }
 
   pub fn cur_anim(&self) -> AnimId {
-    self.cur_anim_id.clone()
+    self.clock.cur_anim_id
   }
 }
 
+impl SpritePart {
+  /// Where the part goes, and whether it's flipped, with the whole frame flipped as given.
+  pub fn place(&self, frame_size: Vector2D<i32>, hflip: bool, vflip: bool) -> (Vector2D<i32>, bool, bool) {
+    let x = if hflip { frame_size.x - self.offset.x - self.size.x } else { self.offset.x };
+    let y = if vflip { frame_size.y - self.offset.y - self.size.y } else { self.offset.y };
+    (Vector2D::new(x, y), self.hflip != hflip, self.vflip != vflip)
+  }
+}
+
+impl<'o> Metasprite<'o> {
+  pub fn new(object: &'o OamManaged, get_next_anim: fn(AnimId) -> MetaAnim, first_anim_enum: AnimId) -> Metasprite<'o> {
+    let mut metasprite = Metasprite {
+      clock: AnimClock::new(get_next_anim, first_anim_enum),
+      objects: Vec::new(),
+      position: Vector2D::new(0, 0),
+      hflip: false,
+      vflip: false,
+    };
+    metasprite.load_frame(object);
+    metasprite
+  }
+
+  /// Advances the animation, returning the events of every frame started since the last draw, as for `AnimPlayer`.
+  pub fn draw(&mut self, object: &'o OamManaged) -> AnimEvents {
+    if self.clock.tick() {
+      self.load_frame(object);
+    }
+    self.clock.take_events()
+  }
+
+  pub fn set_anim(&mut self, anim: AnimId, object: &'o OamManaged) {
+    if self.clock.cur_anim_id != anim {
+      self.force_set_anim(anim, object);
+    }
+  }
+
+  pub fn force_set_anim(&mut self, anim: AnimId, object: &'o OamManaged) {
+    self.clock.set(anim);
+    self.load_frame(object);
+  }
+
+  /// Sets the screen position of the metasprite's top left.
+  pub fn set_position(&mut self, position: Vector2D<i32>) {
+    self.position = position;
+    self.place_parts();
+  }
+
+  pub fn set_hflip(&mut self, hflip: bool) {
+    self.hflip = hflip;
+    self.place_parts();
+  }
+
+  pub fn set_vflip(&mut self, vflip: bool) {
+    self.vflip = vflip;
+    self.place_parts();
+  }
+
+  pub fn cur_anim(&self) -> AnimId {
+    self.clock.cur_anim_id
+  }
+
+  /// Swaps in the current frame's sprites, adding or dropping objects when the number of parts changes.
+  fn load_frame(&mut self, object: &'o OamManaged) {
+    let parts = self.clock.frame().parts;
+    self.objects.truncate(parts.len());
+    for (i, part) in parts.iter().enumerate() {
+      let sprite = part.tag.sprite(part.tag_idx as usize);
+      match self.objects.get_mut(i) {
+        Some(part_object) => {
+          part_object.set_sprite(object.sprite(sprite));
+        }
+        None => {
+          let mut part_object = object.object_sprite(sprite);
+          part_object.show();
+          self.objects.push(part_object);
+        }
+      }
+    }
+    self.place_parts();
+  }
+
+  fn place_parts(&mut self) {
+    let frame = self.clock.frame();
+    for (part_object, part) in self.objects.iter_mut().zip(frame.parts) {
+      let (offset, hflip, vflip) = part.place(frame.size, self.hflip, self.vflip);
+      part_object.set_position(self.position + offset).set_hflip(hflip).set_vflip(vflip);
+    }
+  }
+}
 
 pub struct AnimOffset(pub Vector2D<i32>);
 
@@ -174,7 +395,81 @@ pub mod system {
     player.sprite.set_position(pos);
   }
 
-  pub fn draw<'o>(player: &mut AnimPlayer<'o>, object: &'o OamManaged) {
-    player.draw(object);
+  pub fn position_metasprite<'o>(metasprite: &mut Metasprite<'o>, pos: &Pos, anim_offset: Option<&AnimOffset>, camera: &Camera) {
+    let pos = (pos.0 - camera.position()).trunc();
+    metasprite.set_position(anim_offset.map_or(pos, |anim_offset| pos - anim_offset.0));
+  }
+
+  pub fn draw<'o>(player: &mut AnimPlayer<'o>, object: &'o OamManaged) -> AnimEvents {
+    player.draw(object)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  static TAG: &Tag = &Tag;
+
+  fn part(offset: (i32, i32), size: (i32, i32)) -> SpritePart {
+    SpritePart { tag: TAG, tag_idx: 0, offset: offset.into(), size: size.into(), hflip: false, vflip: false }
+  }
+
+  #[test]
+  fn flipping_mirrors_parts_across_the_frame() {
+    let wing = part((0, 4), (16, 16));
+    let size = Vector2D::new(48, 32);
+    assert!(wing.place(size, false, false) == (Vector2D::new(0, 4), false, false));
+    assert!(wing.place(size, true, false) == (Vector2D::new(32, 4), true, false));
+    assert!(wing.place(size, true, true) == (Vector2D::new(32, 12), true, true));
+
+    let flipped_wing = SpritePart { hflip: true, ..wing };
+    assert!(flipped_wing.place(size, true, false) == (Vector2D::new(32, 4), false, false));
+  }
+
+  #[test]
+  fn clock_steps_frames_then_chains_or_holds() {
+    static FRAMES: &[MetaFrame] = &[
+      MetaFrame { parts: &[], size: Vector2D { x: 16, y: 16 }, duration: 2, events: AnimEvents::NONE },
+      MetaFrame { parts: &[], size: Vector2D { x: 16, y: 16 }, duration: 1, events: AnimEvents::NONE },
+    ];
+    fn get_next_anim(anim: AnimId) -> MetaAnim {
+      MetaAnim { frames: FRAMES, next_anim: if anim.0 == 0 { Some(AnimId(1)) } else { None } }
+    }
+
+    let mut clock = AnimClock::new(get_next_anim, AnimId(0));
+    assert!(!clock.tick());
+    assert!(clock.tick());
+    assert_eq!(clock.frame_idx, 1);
+    assert!(clock.tick());
+    assert!(clock.cur_anim_id == AnimId(1) && clock.frame_idx == 0);
+    clock.tick();
+    clock.tick();
+    assert!(!clock.tick());
+    assert_eq!(clock.frame_idx, 1);
+  }
+
+  #[test]
+  fn events_are_reported_once_per_frame_start() {
+    const FOOTSTEP: AnimEvents = AnimEvents::bit(0);
+    const DUST: AnimEvents = AnimEvents::bit(1);
+    static FRAMES: &[Frame] = &[
+      Frame { tag: TAG, tag_idx: 0, duration: 2, events: FOOTSTEP.union(DUST) },
+      Frame { tag: TAG, tag_idx: 1, duration: 1, events: AnimEvents::NONE },
+      Frame { tag: TAG, tag_idx: 2, duration: 1, events: FOOTSTEP },
+    ];
+    fn get_next_anim(_: AnimId) -> Anim {
+      Anim { frames: FRAMES, next_anim: Some(AnimId(0)) }
+    }
+
+    let object = OamManaged::new();
+    let mut player = AnimPlayer::new(&object, get_next_anim, AnimId(0));
+    let events: Vec<AnimEvents> = (0..5).map(|_| player.draw(&object)).collect();
+    assert!(events[0].contains(FOOTSTEP) && events[0].contains(DUST));
+    assert!(events[1].is_empty());
+    assert_eq!(&events[2..], &[FOOTSTEP, FOOTSTEP.union(DUST), AnimEvents::NONE]);
+
+    player.force_set_anim(AnimId(0), &object);
+    assert_eq!(player.draw(&object), FOOTSTEP.union(DUST));
   }
 }
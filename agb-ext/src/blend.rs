@@ -1,7 +1,6 @@
-use agb::{
-  display::blend::{Blend, Layer},
-  fixnum::Num,
-};
+use alloc::vec::Vec;
+use agb_fixnum::Num;
+use crate::hw::{Blend, BlendMode, Layer, BackgroundID};
 
 type OpacityNum = Num<u8, 4>;
 
@@ -16,18 +15,33 @@ mod opacity_num {
 pub struct ManagedBlend<'o> {
   blend: Blend<'o>,
   top_opacity: OpacityNum,
+  backgrounds: Vec<(Layer, BackgroundID)>,
+  objects: Option<Layer>,
 }
 
 impl<'o> ManagedBlend<'o> {
-  pub fn new(blend: Blend<'o>) -> Self {
+  pub fn new(mut blend: Blend<'o>) -> Self {
+    blend.set_blend_mode(BlendMode::Normal);
     let mut managed_blend = Self {
       blend,
       top_opacity: opacity_num::ONE,
+      backgrounds: Vec::new(),
+      objects: None,
     };
     managed_blend.update_blend_weight();
     managed_blend
   }
 
+  pub fn set_background_enable(&mut self, layer: Layer, background: BackgroundID) {
+    self.backgrounds.push((layer, background));
+    self.update_targets();
+  }
+
+  pub fn set_object_enable(&mut self, layer: Layer) {
+    self.objects = Some(layer);
+    self.update_targets();
+  }
+
   pub fn get_top_opacity(&self) -> OpacityNum {
     self.top_opacity
   }
@@ -51,6 +65,16 @@ impl<'o> ManagedBlend<'o> {
     self.blend.commit();
   }
 
+  fn update_targets(&mut self) {
+    self.blend.reset_targets();
+    for (layer, background) in self.backgrounds.iter() {
+      self.blend.set_background_enable(*layer, *background, true);
+    }
+    if let Some(layer) = self.objects {
+      self.blend.set_object_enable(layer, true);
+    }
+  }
+
   fn update_blend_weight(&mut self) {
     self.blend.set_blend_weight(Layer::Top, self.top_opacity);
     self.blend.set_blend_weight(Layer::Bottom, opacity_num::ONE - self.top_opacity);
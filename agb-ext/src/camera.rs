@@ -1,52 +1,480 @@
-use agb::fixnum::Vector2D;
-use crate::math::{PosNum, ZERO};
+use agb_fixnum::{Vector2D, Rect};
+use crate::hw::Tri;
+use crate::math::{PosNum, ZERO, const_num_i32};
 
 const SCREEN_W: i32 = 240;
 const SCREEN_H: i32 = 160;
-const SMOOTHED_MOVEMENT: PosNum = PosNum::from_raw(15 << 8);
+/// Furthest the view shakes from the camera's position at full trauma, in pixels.
+const MAX_SHAKE: i32 = 6;
+/// Trauma lost each frame, so a full shake settles in about half a second.
+const TRAUMA_DECAY: PosNum = PosNum::from_raw(1 << 3);
+const FULL_TRAUMA: PosNum = const_num_i32(1, 0);
+
+/// How the camera trails its target in `Camera::follow`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Follow {
+  /// The box around the middle of the screen the target can move within without moving the camera.
+  pub dead_zone: Vector2D<PosNum>,
+  /// How far ahead of the target the camera looks in the direction it last moved.
+  pub look_ahead: PosNum,
+  /// How far the look-ahead swings each frame when the target turns around.
+  pub look_ahead_speed: PosNum,
+  /// The furthest the camera moves each frame on either axis.
+  pub max_speed: PosNum,
+}
+
+/// What `Camera::follow` tracks each frame.
+#[derive(Clone, Copy, PartialEq)]
+pub struct FollowTarget {
+  pub center: Vector2D<PosNum>,
+  pub direction: Tri,
+  pub on_ground: bool,
+}
+
+/// A scripted move to a point of interest, which holds there for a while before heading back to the target.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Pan {
+  center: Vector2D<PosNum>,
+  speed: PosNum,
+  hold_frames: u16,
+  returning: bool,
+}
 
 pub struct Camera {
   pos: Vector2D<PosNum>,
-  max_limit: Vector2D<PosNum>,
+  limits: Rect<PosNum>,
+  area_limits: Option<Rect<PosNum>>,
+  follow: Follow,
+  focus: Vector2D<PosNum>,
+  look_ahead: PosNum,
+  focus_override: Option<Vector2D<PosNum>>,
+  pan: Option<Pan>,
+  trauma: PosNum,
+  shake_seed: u32,
+  shake: Vector2D<PosNum>,
+}
+
+impl Follow {
+  pub const DEFAULT: Follow = Follow {
+    dead_zone: Vector2D { x: const_num_i32(16, 0), y: const_num_i32(48, 0) },
+    look_ahead: const_num_i32(32, 0),
+    look_ahead_speed: const_num_i32(1, 0),
+    max_speed: const_num_i32(15, 0),
+  };
 }
 
 impl Camera {
+  /// Starts at the origin with limits of a single screen, until `set_limits` is given the room's bounds.
   pub fn new() -> Self {
     Camera {
       pos: Vector2D::new(ZERO, ZERO),
-      max_limit: Vector2D::new(PosNum::new(SCREEN_W + 16), PosNum::new(SCREEN_H)),
+      limits: Rect::new(Vector2D::new(ZERO, ZERO), Vector2D::new(PosNum::new(SCREEN_W), PosNum::new(SCREEN_H))),
+      area_limits: None,
+      follow: Follow::DEFAULT,
+      focus: Vector2D::new(PosNum::new(SCREEN_W / 2), PosNum::new(SCREEN_H / 2)),
+      look_ahead: ZERO,
+      focus_override: None,
+      pan: None,
+      trauma: ZERO,
+      shake_seed: 0x2545_F491,
+      shake: Vector2D::new(ZERO, ZERO),
     }
   }
 
   pub fn center_on(&mut self, pos: Vector2D<PosNum>) {
+    self.focus = pos;
     self.set_position(pos - Vector2D::new(SCREEN_W / 2, SCREEN_H / 2).into());
   }
 
   pub fn set_position(&mut self, pos: Vector2D<PosNum>) {
-    self.pos.x = pos.x.clamp(ZERO, self.max_limit.x - PosNum::new(SCREEN_W));
-    self.pos.y = pos.y.clamp(ZERO, self.max_limit.y - PosNum::new(SCREEN_H));
+    self.pos = self.clamp_to_limits(pos);
   }
 
   pub fn smoothed_center_on(&mut self, pos: Vector2D<PosNum>) {
     self.smoothed_set_position(pos - Vector2D::new(SCREEN_W / 2, SCREEN_H / 2).into());
   }
 
+  /// Moves toward `pos` by at most `Follow::max_speed` on each axis. Only the destination is held to the area limits,
+  /// so the camera glides into a new area rather than jumping to it.
   pub fn smoothed_set_position(&mut self, pos: Vector2D<PosNum>) {
-    let offset = pos - self.pos;
-    let smoothed_offset = Vector2D::new(offset.x.clamp(-SMOOTHED_MOVEMENT, SMOOTHED_MOVEMENT), offset.y.clamp(-SMOOTHED_MOVEMENT, SMOOTHED_MOVEMENT));
-    self.set_position(self.pos + smoothed_offset);
+    self.move_toward(pos, self.follow.max_speed);
+  }
+
+  /// Trails `target` platformer style. The camera leads in the direction the target last moved and only scrolls
+  /// once the target leaves the dead zone. While airborne the camera holds its height unless the target leaves the
+  /// dead zone vertically, then settles on the platform the target lands on.
+  pub fn follow(&mut self, target: FollowTarget) {
+    let follow = self.follow;
+    if target.direction != Tri::Zero {
+      let goal = follow.look_ahead * PosNum::new(target.direction as i32);
+      self.look_ahead = approach(self.look_ahead, goal, follow.look_ahead_speed);
+    }
+    let half_dead_zone = follow.dead_zone / 2;
+    let x = target.center.x + self.look_ahead;
+    self.focus.x = self.focus.x.clamp(x - half_dead_zone.x, x + half_dead_zone.x);
+    self.focus.y = if target.on_ground {
+      target.center.y
+    } else {
+      self.focus.y.clamp(target.center.y - half_dead_zone.y, target.center.y + half_dead_zone.y)
+    };
+
+    let half_screen: Vector2D<PosNum> = Vector2D::new(SCREEN_W / 2, SCREEN_H / 2).into();
+    match self.pan {
+      Some(mut pan) => {
+        let center = if pan.returning { self.focus_override.unwrap_or(self.focus) } else { pan.center };
+        let arrived = self.move_toward(center - half_screen, pan.speed);
+        if arrived && pan.returning {
+          self.pan = None;
+          return;
+        }
+        if arrived && pan.hold_frames > 0 {
+          pan.hold_frames -= 1;
+        } else if arrived {
+          pan.returning = true;
+        }
+        self.pan = Some(pan);
+      }
+      None => {
+        self.smoothed_center_on(self.focus_override.unwrap_or(self.focus));
+      }
+    }
+  }
+
+  /// Moves the camera to centre on `center` at `speed` pixels per frame, holds it there for `hold_frames`, then
+  /// brings it back to the target at the same speed. The target is still tracked meanwhile, so the camera returns
+  /// to wherever it has got to.
+  pub fn pan_to(&mut self, center: Vector2D<PosNum>, speed: PosNum, hold_frames: u16) {
+    self.pan = Some(Pan { center, speed, hold_frames, returning: false });
+  }
+
+  pub fn is_panning(&self) -> bool {
+    self.pan.is_some()
+  }
+
+  /// Centres on `focus` instead of the target until cleared with `None`, such as on a boss for the length of a fight.
+  pub fn set_focus_override(&mut self, focus: Option<Vector2D<PosNum>>) {
+    self.focus_override = focus;
+  }
+
+  /// Shakes the screen harder the more trauma has built up, up to a full shake at 1. Trauma wears off on its own.
+  pub fn add_trauma(&mut self, trauma: PosNum) {
+    self.trauma = (self.trauma + trauma).clamp(ZERO, FULL_TRAUMA);
+  }
+
+  /// Advances the screen shake. Call once a frame, whether or not anything is being followed.
+  pub fn tick(&mut self) {
+    if self.trauma == ZERO {
+      self.shake = Vector2D::new(ZERO, ZERO);
+      return;
+    }
+    // Shake grows with the square of trauma, so small knocks stay subtle.
+    let strength = PosNum::new(MAX_SHAKE) * self.trauma * self.trauma;
+    self.shake = Vector2D::new(strength * self.shake_noise(), strength * self.shake_noise());
+    self.trauma = (self.trauma - TRAUMA_DECAY).max(ZERO);
+  }
+
+  pub fn set_follow(&mut self, follow: Follow) {
+    self.follow = follow;
   }
 
+  /// Where the view is drawn from, including any screen shake. Backgrounds and sprites should both be placed relative
+  /// to this so they shake together.
   pub fn position(&self) -> Vector2D<PosNum> {
-    self.pos
+    let shaken = self.pos + self.shake.trunc().into();
+    let clamped = self.clamp_to_room(shaken);
+    // Rooms narrower or shorter than the screen are letterboxed, so they can shake without showing past their edges.
+    Vector2D::new(
+      if self.limits.size.x < PosNum::new(SCREEN_W) { shaken.x } else { clamped.x },
+      if self.limits.size.y < PosNum::new(SCREEN_H) { shaken.y } else { clamped.y },
+    )
   }
 
   pub fn position_i16(&self) -> Vector2D<i16> {
-    let trunc_pos = self.pos.trunc();
+    let trunc_pos = self.position().trunc();
     Vector2D::new(trunc_pos.x as i16, trunc_pos.y as i16)
   }
 
-  pub fn set_limits(&mut self, max_limit: Vector2D<PosNum>) {
-    self.max_limit = max_limit;
+  /// Keeps the camera within the room's bounds in world coordinates, which don't have to start at the origin. A room
+  /// narrower or shorter than the screen is centred on that axis instead, see `letterbox`.
+  pub fn set_limits(&mut self, limits: Rect<PosNum>) {
+    self.limits = limits;
+  }
+
+  pub fn limits(&self) -> Rect<PosNum> {
+    self.limits
+  }
+
+  /// The part of the screen the room covers when it's too small to fill it, so everything outside can be hidden.
+  pub fn letterbox(&self) -> Option<Rect<i32>> {
+    let screen = Vector2D::new(SCREEN_W, SCREEN_H);
+    let size = self.limits.size.trunc();
+    if size.x >= screen.x && size.y >= screen.y {
+      return None;
+    }
+    let top_left = (self.limits.position - self.position()).trunc();
+    let bottom_right = top_left + size;
+    let top_left = Vector2D::new(top_left.x.clamp(0, screen.x), top_left.y.clamp(0, screen.y));
+    let bottom_right = Vector2D::new(bottom_right.x.clamp(0, screen.x), bottom_right.y.clamp(0, screen.y));
+    Some(Rect::new(top_left, bottom_right - top_left))
+  }
+
+  /// Keeps the camera within part of the room, such as one placed with a `CameraBounds` object, until cleared with
+  /// `None`.
+  pub fn set_area_limits(&mut self, area_limits: Option<Rect<PosNum>>) {
+    self.area_limits = area_limits;
+  }
+
+  /// Returns whether the camera has reached `pos`, or as close as the limits allow.
+  fn move_toward(&mut self, pos: Vector2D<PosNum>, speed: PosNum) -> bool {
+    let offset = self.clamp_to_limits(pos) - self.pos;
+    let smoothed_offset = Vector2D::new(offset.x.clamp(-speed, speed), offset.y.clamp(-speed, speed));
+    self.pos = self.clamp_to_room(self.pos + smoothed_offset);
+    smoothed_offset == offset
+  }
+
+  /// A deterministic value between -1 and 1, so replays shake the same way.
+  fn shake_noise(&mut self) -> PosNum {
+    self.shake_seed ^= self.shake_seed << 13;
+    self.shake_seed ^= self.shake_seed >> 17;
+    self.shake_seed ^= self.shake_seed << 5;
+    PosNum::from_raw((self.shake_seed >> 8 & 0x1FF) as i32 - 256)
+  }
+
+  fn clamp_to_room(&self, pos: Vector2D<PosNum>) -> Vector2D<PosNum> {
+    clamp_within(pos, self.limits)
+  }
+
+  fn clamp_to_limits(&self, pos: Vector2D<PosNum>) -> Vector2D<PosNum> {
+    let pos = match self.area_limits {
+      Some(area) => clamp_within(pos, area),
+      None => pos,
+    };
+    self.clamp_to_room(pos)
+  }
+}
+
+/// Keeps a screen at `pos` inside `bounds`, or centres it on any axis where `bounds` is smaller than the screen.
+fn clamp_within(pos: Vector2D<PosNum>, bounds: Rect<PosNum>) -> Vector2D<PosNum> {
+  let clamp_axis = |pos: PosNum, min: PosNum, size: PosNum, screen: i32| {
+    let screen = PosNum::new(screen);
+    if size < screen {
+      min - (screen - size) / 2
+    } else {
+      pos.clamp(min, min + size - screen)
+    }
+  };
+  Vector2D::new(
+    clamp_axis(pos.x, bounds.position.x, bounds.size.x, SCREEN_W),
+    clamp_axis(pos.y, bounds.position.y, bounds.size.y, SCREEN_H),
+  )
+}
+
+fn approach(from: PosNum, to: PosNum, step: PosNum) -> PosNum {
+  if from < to {
+    (from + step).min(to)
+  } else {
+    (from - step).max(to)
+  }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Parallax {
+  pub ratio: Vector2D<PosNum>,
+  pub auto_scroll: Vector2D<PosNum>,
+  offset: Vector2D<PosNum>,
+}
+
+impl Parallax {
+  pub const LOCKED: Parallax = Parallax::new(const_num_i32(1, 0), const_num_i32(1, 0));
+
+  pub const fn new(x_ratio: PosNum, y_ratio: PosNum) -> Self {
+    Parallax {
+      ratio: Vector2D { x: x_ratio, y: y_ratio },
+      auto_scroll: Vector2D { x: ZERO, y: ZERO },
+      offset: Vector2D { x: ZERO, y: ZERO },
+    }
+  }
+
+  pub const fn with_auto_scroll(self, x_speed: PosNum, y_speed: PosNum) -> Self {
+    Parallax {
+      auto_scroll: Vector2D { x: x_speed, y: y_speed },
+      ..self
+    }
+  }
+
+  pub fn tick(&mut self) {
+    self.offset += self.auto_scroll;
+  }
+
+  pub fn scroll_pos(&self, camera: &Camera) -> Vector2D<i32> {
+    let pos = camera.position();
+    (Vector2D::new(pos.x * self.ratio.x, pos.y * self.ratio.y) + self.offset).trunc()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pos(x: i32, y: i32) -> Vector2D<PosNum> {
+    Vector2D::new(PosNum::new(x), PosNum::new(y))
+  }
+
+  fn room(w: i32, h: i32) -> Rect<PosNum> {
+    Rect::new(pos(0, 0), pos(w, h))
+  }
+
+  #[test]
+  fn position_is_clamped_to_limits() {
+    let mut camera = Camera::new();
+    camera.set_limits(room(480, 320));
+    camera.set_position(pos(1000, -5));
+    assert_eq!(camera.position(), pos(240, 0));
+  }
+
+  #[test]
+  fn center_on_offsets_by_half_the_screen() {
+    let mut camera = Camera::new();
+    camera.set_limits(room(480, 320));
+    camera.center_on(pos(240, 160));
+    assert_eq!(camera.position(), pos(120, 80));
+    assert_eq!(camera.position_i16(), Vector2D::new(120, 80));
+  }
+
+  #[test]
+  fn smoothed_movement_is_capped_per_frame() {
+    let mut camera = Camera::new();
+    camera.set_limits(room(480, 320));
+    camera.smoothed_set_position(pos(100, 4));
+    assert_eq!(camera.position(), pos(15, 4));
+  }
+
+  fn target(x: i32, y: i32, direction: Tri, on_ground: bool) -> FollowTarget {
+    FollowTarget { center: pos(x, y), direction, on_ground }
+  }
+
+  fn follower() -> Camera {
+    let mut camera = Camera::new();
+    camera.set_limits(room(1024, 1024));
+    camera.set_follow(Follow { look_ahead: ZERO, ..Follow::DEFAULT });
+    camera.center_on(pos(400, 400));
+    camera
+  }
+
+  #[test]
+  fn dead_zone_absorbs_small_movements() {
+    let mut camera = follower();
+    camera.follow(target(407, 400, Tri::Positive, true));
+    assert_eq!(camera.position(), pos(280, 320));
+    camera.follow(target(420, 400, Tri::Positive, true));
+    assert_eq!(camera.position(), pos(292, 320));
+  }
+
+  #[test]
+  fn looks_ahead_in_the_direction_of_movement() {
+    let mut camera = follower();
+    camera.set_follow(Follow { dead_zone: pos(0, 0), ..Follow::DEFAULT });
+    for _ in 0..40 {
+      camera.follow(target(400, 400, Tri::Negative, true));
+    }
+    assert_eq!(camera.position(), pos(248, 320));
+    // Standing still keeps looking the same way.
+    camera.follow(target(400, 400, Tri::Zero, true));
+    assert_eq!(camera.position(), pos(248, 320));
+  }
+
+  #[test]
+  fn height_holds_while_airborne_and_snaps_to_the_platform() {
+    let mut camera = follower();
+    camera.follow(target(400, 380, Tri::Zero, false));
+    assert_eq!(camera.position(), pos(280, 320));
+    camera.follow(target(400, 370, Tri::Zero, false));
+    assert_eq!(camera.position(), pos(280, 314));
+    camera.follow(target(400, 370, Tri::Zero, true));
+    assert_eq!(camera.position(), pos(280, 299));
+  }
+
+  #[test]
+  fn glides_into_area_limits() {
+    let mut camera = follower();
+    camera.set_area_limits(Some(Rect::new(pos(320, 0), pos(480, 1024))));
+    camera.center_on(pos(400, 400));
+    assert_eq!(camera.position(), pos(320, 320));
+
+    camera.set_area_limits(Some(Rect::new(pos(0, 0), pos(240, 1024))));
+    camera.smoothed_center_on(pos(400, 400));
+    assert_eq!(camera.position(), pos(305, 320));
+  }
+
+  #[test]
+  fn pans_out_holds_and_comes_back() {
+    let mut camera = follower();
+    let player = target(400, 400, Tri::Zero, true);
+    camera.pan_to(pos(460, 400), const_num_i32(30, 0), 1);
+    camera.follow(player);
+    assert_eq!(camera.position(), pos(310, 320));
+    camera.follow(player);
+    assert_eq!(camera.position(), pos(340, 320));
+    camera.follow(player);
+    assert_eq!(camera.position(), pos(340, 320));
+    camera.follow(player);
+    assert_eq!(camera.position(), pos(310, 320));
+    camera.follow(player);
+    assert_eq!(camera.position(), pos(280, 320));
+    assert!(!camera.is_panning());
+  }
+
+  #[test]
+  fn focus_override_replaces_the_target() {
+    let mut camera = follower();
+    camera.set_focus_override(Some(pos(410, 390)));
+    camera.follow(target(600, 400, Tri::Zero, true));
+    assert_eq!(camera.position(), pos(290, 310));
+    camera.set_focus_override(None);
+    camera.follow(target(600, 400, Tri::Zero, true));
+    assert_eq!(camera.position(), pos(305, 320));
+  }
+
+  #[test]
+  fn shake_moves_the_view_and_settles() {
+    let mut camera = follower();
+    camera.add_trauma(const_num_i32(1, 0));
+    camera.tick();
+    let shaken = camera.position() - pos(280, 320);
+    assert!(shaken != pos(0, 0) && shaken.x.abs() <= PosNum::new(MAX_SHAKE) && shaken.y.abs() <= PosNum::new(MAX_SHAKE));
+    for _ in 0..32 {
+      camera.tick();
+    }
+    assert_eq!(camera.position(), pos(280, 320));
+  }
+
+  #[test]
+  fn rooms_away_from_the_origin_clamp_to_their_own_bounds() {
+    let mut camera = Camera::new();
+    camera.set_limits(Rect::new(pos(1000, -200), pos(480, 320)));
+    camera.set_position(pos(0, 0));
+    assert_eq!(camera.position(), pos(1000, -40));
+    camera.set_position(pos(2000, -1000));
+    assert_eq!(camera.position(), pos(1240, -200));
+    assert_eq!(camera.letterbox(), None);
+  }
+
+  #[test]
+  fn small_rooms_are_centred_and_letterboxed() {
+    let mut camera = Camera::new();
+    camera.set_limits(Rect::new(pos(16, 0), pos(160, 320)));
+    camera.set_position(pos(500, 40));
+    assert_eq!(camera.position(), pos(-24, 40));
+    assert_eq!(camera.letterbox(), Some(Rect::new(Vector2D::new(40, 0), Vector2D::new(160, 160))));
+  }
+
+  #[test]
+  fn parallax_scales_camera_position() {
+    let mut camera = Camera::new();
+    camera.set_limits(room(480, 320));
+    camera.set_position(pos(100, 50));
+    let mut parallax = Parallax::new(const_num_i32(0, 5), const_num_i32(0, 5)).with_auto_scroll(const_num_i32(1, 0), ZERO);
+    parallax.tick();
+    assert_eq!(parallax.scroll_pos(&camera), Vector2D::new(51, 25));
   }
 }
@@ -1,8 +1,12 @@
 use core::convert::Into;
-use agb::{fixnum::{Vector2D, Num, Rect}, include_wav, input::ButtonController};
+use alloc::collections::BTreeMap;
+use agb_fixnum::{Vector2D, Num, Rect};
+use crate::input::{Action, ActionState};
 use crate::collision::CollisionLayer::Pipe;
 use crate::math::{PosNum, const_num_i32, ZERO, MIN_INC};
+#[cfg(feature = "agb")]
 use crate::ecs::Entity as EcsEntity;
+use crate::rle::RleLayer;
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum CollideTileType {
@@ -41,6 +45,12 @@ pub struct Size(pub Vector2D<PosNum>);
 #[derive(Clone, Copy, PartialEq)]
 pub struct OnGround(pub bool);
 
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct WallContact {
+  pub left: bool,
+  pub right: bool,
+}
+
 #[derive(Clone, Debug)]
 pub struct Collision {
   pub x_seam: Option<i32>,
@@ -49,9 +59,12 @@ pub struct Collision {
 }
 
 pub struct CollideTilemap {
-  pub data: &'static [CollideTileType],
+  pub data: RleLayer,
   pub width: usize,
   pub height: usize,
+  /// The world tile the map's top left tile sits at. Tile positions passed in and out are world tiles.
+  pub origin: Vector2D<i32>,
+  overrides: BTreeMap<usize, CollideTileType>,
 }
 
 
@@ -64,10 +77,13 @@ pub trait Entity {
 }
 
 pub trait ControllableEntity: Entity {
-  fn propose_movement(&mut self, input: Option<&ButtonController>) -> Vector2D<PosNum>;
+  /// The game's actions, so movement follows the player's bindings rather than raw buttons.
+  type Action: Action;
+
+  fn propose_movement(&mut self, actions: Option<&ActionState<Self::Action>>) -> Vector2D<PosNum>;
 
-  fn physics_process(&mut self, tilemap: &CollideTilemap, input: Option<&ButtonController>) {
-    let movement = self.propose_movement(input);
+  fn physics_process(&mut self, tilemap: &CollideTilemap, actions: Option<&ActionState<Self::Action>>) {
+    let movement = self.propose_movement(actions);
     let hitbox = self.col_rect();
     let col = tilemap.get_collision_seams(movement, hitbox, self.col_layer());
     self.move_by(move_and_collide(movement, hitbox, &col), col.snap_to_ground);
@@ -86,19 +102,26 @@ pub mod system {
     vel.0 = vel.0 + acc.0;
   }
 
+  #[cfg(feature = "agb")]
   pub fn print_pos(en: &EcsEntity, pos: &Pos) {
     agb::println!("{:?}: {:?}", en, pos.0);
   }
 
-  pub fn physics_process(pos: &Pos, vel: &mut Vel, size: &Size, col_layer: &CollisionLayer, on_ground: Option<&mut OnGround>, tilemap: &CollideTilemap) {
+  pub fn physics_process(pos: &Pos, vel: &mut Vel, size: &Size, col_layer: &CollisionLayer, on_ground: Option<&mut OnGround>, wall_contact: Option<&mut WallContact>, tilemap: &CollideTilemap) {
     let hitbox = Rect::new(pos.0, size.0);
     let col = tilemap.get_collision_seams(vel.0, hitbox, *col_layer);
     let new_vel = move_and_collide(vel.0, hitbox, &col);
 
-    if vel.0.y > new_vel.y {
-      if let Some(on_ground) = on_ground {
-        on_ground.0 = true;
-      }
+    // Only a floor stopping this frame's fall counts, so walking off a ledge or jumping clears it
+    if let Some(on_ground) = on_ground {
+      on_ground.0 = vel.0.y > new_vel.y;
+    }
+    // Probed on both sides after moving, so standing against a wall counts whichever way the entity is pushing
+    if let Some(wall_contact) = wall_contact {
+      let moved = Rect::new(pos.0 + new_vel, size.0);
+      let probe = |dx: i32| tilemap.touches_wall(Rect::new(moved.position + (PosNum::new(dx), ZERO).into(), moved.size), *col_layer);
+      wall_contact.left = probe(-1);
+      wall_contact.right = probe(1);
     }
 
     vel.0 = new_vel;
@@ -136,6 +159,104 @@ fn move_and_collide(movement: Vector2D<PosNum>, hitbox: Rect<PosNum>, col: &Coll
 impl CollideTilemap {
   const PX_PER_TILE: PosNum = const_num_i32(16, 0);
 
+  pub fn new(data: RleLayer, width: usize, height: usize) -> Self {
+    CollideTilemap {
+      data,
+      width,
+      height,
+      origin: Vector2D::new(0, 0),
+      overrides: BTreeMap::new(),
+    }
+  }
+
+  /// Places the map in the world, for rooms that don't start at the top left of the world.
+  pub fn with_origin(self, origin: Vector2D<i32>) -> Self {
+    CollideTilemap {
+      origin,
+      ..self
+    }
+  }
+
+  /// The area the map covers, in world pixels.
+  pub fn bounds(&self) -> Rect<PosNum> {
+    Rect::new(
+      (self.origin * 16).change_base(),
+      (PosNum::new(self.width as i32 * 16), PosNum::new(self.height as i32 * 16)).into(),
+    )
+  }
+
+  pub fn tile_at(&self, pos: Vector2D<i32>) -> CollideTileType {
+    match self.local(pos) {
+      Some((x, y)) => self.tile(x, y),
+      None => CollideTileType::Pass,
+    }
+  }
+
+  pub fn set_tile(&mut self, pos: Vector2D<i32>, tile: CollideTileType) {
+    if let Some((x, y)) = self.local(pos) {
+      self.overrides.insert(x + y * self.width, tile);
+    }
+  }
+
+  /// The map's own tile at a world tile, if it's inside the map.
+  fn local(&self, pos: Vector2D<i32>) -> Option<(usize, usize)> {
+    let pos = pos - self.origin;
+    if pos.x < 0 || pos.x >= self.width as i32 || pos.y < 0 || pos.y >= self.height as i32 {
+      None
+    } else {
+      Some((pos.x as usize, pos.y as usize))
+    }
+  }
+
+  pub fn clear_overrides(&mut self) {
+    self.overrides.clear();
+  }
+
+  fn tile(&self, x: usize, y: usize) -> CollideTileType {
+    match self.overrides.get(&(x + y * self.width)) {
+      Some(tile) => *tile,
+      None => CollideTileType::from_u8(self.data.get(x, y)[0]).unwrap_or(CollideTileType::Pass),
+    }
+  }
+
+  pub fn is_free(&self, hitbox: Rect<PosNum>, layer: CollisionLayer) -> bool {
+    let left_x = (hitbox.position.x / Self::PX_PER_TILE).floor();
+    let right_x = ((hitbox.position.x + hitbox.size.x - MIN_INC) / Self::PX_PER_TILE).floor();
+    let up_y = (hitbox.position.y / Self::PX_PER_TILE).floor();
+    let down_y = ((hitbox.position.y + hitbox.size.y - MIN_INC) / Self::PX_PER_TILE).floor();
+    for xi in left_x..=right_x {
+      for yi in up_y..=down_y {
+        if self.tile_at((xi, yi).into()).is_tile_colliding((xi, yi).into(), hitbox, layer) {
+          return false;
+        }
+      }
+    }
+    true
+  }
+
+  /// Whether the hitbox overlaps a wall, as opposed to a slope or floor, that movement would be stopped by.
+  fn touches_wall(&self, hitbox: Rect<PosNum>, layer: CollisionLayer) -> bool {
+    let left_x = (hitbox.position.x / Self::PX_PER_TILE).floor();
+    let right_x = ((hitbox.position.x + hitbox.size.x - MIN_INC) / Self::PX_PER_TILE).floor();
+    let up_y = (hitbox.position.y / Self::PX_PER_TILE).floor();
+    let down_y = ((hitbox.position.y + hitbox.size.y - MIN_INC) / Self::PX_PER_TILE).floor();
+    (left_x..=right_x).any(|xi| (up_y..=down_y).any(|yi| {
+      self.movement_tile((xi, yi).into())
+        .is_some_and(|tile| tile.is_wall(layer) && tile.is_tile_colliding((xi, yi).into(), hitbox, layer))
+    }))
+  }
+
+  /// The tile movement collides with at a world tile. The map's outer row and column are left out, as movement has
+  /// always ignored them.
+  fn movement_tile(&self, pos: Vector2D<i32>) -> Option<CollideTileType> {
+    let local = pos - self.origin;
+    if local.x > 0 && local.x < self.width as i32 && local.y > 0 && local.y < self.height as i32 {
+      Some(self.tile(local.x as usize, local.y as usize))
+    } else {
+      None
+    }
+  }
+
   fn get_collision_seams(&self, movement: Vector2D<PosNum>, hitbox: Rect<PosNum>, layer: CollisionLayer) -> Collision {
     let moving_left = movement.x < ZERO;
     let moving_up = movement.y < ZERO;
@@ -186,9 +307,7 @@ impl CollideTilemap {
     let mut snap_to_ground = false;
     for xi in tile_left_x..=tile_right_x {
       for yi in tile_up_y..=tile_down_y {
-        if xi > 0 && xi < self.width as i32 && yi > 0 && yi < self.height as i32 {
-          let tile_idx = xi as usize + yi as usize * self.width;
-          let tile: CollideTileType = self.data[tile_idx];
+        if let Some(tile) = self.movement_tile((xi, yi).into()) {
           if tile.is_tile_colliding((xi, yi).into(), adjusted_hitbox, layer) {
             match (entered_x == Some(xi), entered_y == Some(yi)) {
               (false, false) => {
@@ -263,6 +382,14 @@ impl CollideTilemap {
 }
 
 impl CollideTileType {
+  pub fn from_u8(value: u8) -> Option<Self> {
+    [
+      Self::Pass, Self::Solid, Self::LWall, Self::RWall, Self::Pipe, Self::RSteepSlope,
+      Self::RLowSlope1, Self::RLowSlope2, Self::PipeSolid, Self::LSteepSlope, Self::LLowSlope1, Self::LLowSlope2,
+    ].get(value as usize)
+      .copied()
+  }
+
   pub fn is_nonstandard_hitbox(self) -> bool {
     match self {
       Self::LWall => true,
@@ -279,6 +406,15 @@ impl CollideTileType {
     ].contains(&self)
   }
 
+  /// Tiles with a vertical side, which entities can cling to.
+  pub fn is_wall(self, layer: CollisionLayer) -> bool {
+    match self {
+      Self::Solid | Self::LWall | Self::RWall => true,
+      Self::PipeSolid => layer == CollisionLayer::Normal,
+      _ => false,
+    }
+  }
+
   pub fn is_tile_colliding(self, pos: Vector2D<i32>, adjusted_hitbox: Rect<PosNum>, layer: CollisionLayer) -> bool {
     match self {
       Self::Pass => layer == CollisionLayer::Pipe,
@@ -377,3 +513,123 @@ impl CollideTileType {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // 4x3 room with a solid floor and a solid right column
+  static DATA: [u8; 8] = [4, 0, 3, 0, 1, 1, 4, 1];
+
+  fn tilemap() -> CollideTilemap {
+    CollideTilemap::new(RleLayer::new(&DATA, &[0, 2, 6], 1), 4, 3)
+  }
+
+  fn vector(x: i32, y: i32) -> Vector2D<PosNum> {
+    Vector2D::new(PosNum::new(x), PosNum::new(y))
+  }
+
+  #[test]
+  fn tiles_outside_the_map_pass() {
+    let tilemap = tilemap();
+    assert!(tilemap.tile_at((0, 2).into()) == CollideTileType::Solid);
+    assert!(tilemap.tile_at((0, 1).into()) == CollideTileType::Pass);
+    assert!(tilemap.tile_at((-1, 2).into()) == CollideTileType::Pass);
+    assert!(tilemap.tile_at((0, 3).into()) == CollideTileType::Pass);
+  }
+
+  #[test]
+  fn origin_moves_the_map_in_the_world() {
+    let mut tilemap = tilemap().with_origin((10, -2).into());
+    assert!(tilemap.tile_at((10, 0).into()) == CollideTileType::Solid);
+    assert!(tilemap.tile_at((0, 2).into()) == CollideTileType::Pass);
+    assert!(tilemap.bounds() == Rect::new(vector(160, -32), vector(64, 48)));
+    tilemap.set_tile((11, 0).into(), CollideTileType::Pass);
+    assert!(tilemap.tile_at((11, 0).into()) == CollideTileType::Pass);
+
+    let pos = Pos(vector(176, -20));
+    let mut vel = Vel(vector(0, 8));
+    let mut on_ground = OnGround(false);
+    system::physics_process(&pos, &mut vel, &Size(vector(16, 16)), &CollisionLayer::Normal, Some(&mut on_ground), None, &tilemap);
+    assert!(vel.0 == vector(0, 8));
+    let pos = Pos(vector(192, -20));
+    system::physics_process(&pos, &mut vel, &Size(vector(16, 16)), &CollisionLayer::Normal, Some(&mut on_ground), None, &tilemap);
+    assert!(vel.0 == vector(0, 4));
+    assert!(on_ground.0);
+  }
+
+  #[test]
+  fn overrides_replace_tiles_until_cleared() {
+    let mut tilemap = tilemap();
+    tilemap.set_tile((1, 2).into(), CollideTileType::Pass);
+    tilemap.set_tile((9, 9).into(), CollideTileType::Solid);
+    assert!(tilemap.tile_at((1, 2).into()) == CollideTileType::Pass);
+    assert!(tilemap.is_free(Rect::new(vector(16, 16), vector(16, 32)), CollisionLayer::Normal));
+    tilemap.clear_overrides();
+    assert!(tilemap.tile_at((1, 2).into()) == CollideTileType::Solid);
+    assert!(!tilemap.is_free(Rect::new(vector(16, 16), vector(16, 32)), CollisionLayer::Normal));
+  }
+
+  #[test]
+  fn falling_stops_on_the_floor() {
+    let tilemap = tilemap();
+    let pos = Pos(vector(16, 12));
+    let mut vel = Vel(vector(0, 8));
+    let mut on_ground = OnGround(false);
+    system::physics_process(&pos, &mut vel, &Size(vector(16, 16)), &CollisionLayer::Normal, Some(&mut on_ground), None, &tilemap);
+    assert!(vel.0 == vector(0, 4));
+    assert!(on_ground.0);
+  }
+
+  #[test]
+  fn leaving_the_floor_clears_on_ground() {
+    let tilemap = tilemap();
+    let pos = Pos(vector(16, 16));
+    let mut on_ground = OnGround(false);
+    let mut vel = Vel(vector(0, 2));
+    system::physics_process(&pos, &mut vel, &Size(vector(16, 16)), &CollisionLayer::Normal, Some(&mut on_ground), None, &tilemap);
+    assert!(on_ground.0);
+    let mut vel = Vel(vector(0, -4));
+    system::physics_process(&pos, &mut vel, &Size(vector(16, 16)), &CollisionLayer::Normal, Some(&mut on_ground), None, &tilemap);
+    assert!(!on_ground.0);
+  }
+
+  #[test]
+  fn touching_a_wall_without_pushing_is_contact() {
+    let tilemap = tilemap();
+    let pos = Pos(vector(32, 16));
+    let mut wall_contact = WallContact::default();
+    for vel in [vector(0, 0), vector(0, -2)] {
+      let mut vel = Vel(vel);
+      system::physics_process(&pos, &mut vel, &Size(vector(16, 16)), &CollisionLayer::Normal, None, Some(&mut wall_contact), &tilemap);
+      assert!(wall_contact.right && !wall_contact.left);
+    }
+    let pos = Pos(vector(16, 16));
+    let mut vel = Vel(vector(0, 0));
+    system::physics_process(&pos, &mut vel, &Size(vector(16, 16)), &CollisionLayer::Normal, None, Some(&mut wall_contact), &tilemap);
+    assert!(!wall_contact.right && !wall_contact.left);
+  }
+
+  #[test]
+  fn slopes_and_the_map_edge_are_not_wall_contact() {
+    let mut tilemap = tilemap();
+    tilemap.set_tile((2, 1).into(), CollideTileType::RSteepSlope);
+    tilemap.set_tile((0, 1).into(), CollideTileType::Solid);
+    let pos = Pos(vector(16, 16));
+    let mut vel = Vel(vector(0, 0));
+    let mut wall_contact = WallContact::default();
+    system::physics_process(&pos, &mut vel, &Size(vector(16, 16)), &CollisionLayer::Normal, None, Some(&mut wall_contact), &tilemap);
+    assert!(!wall_contact.right && !wall_contact.left);
+  }
+
+  #[test]
+  fn walking_into_a_wall_stops_at_its_edge() {
+    let tilemap = tilemap();
+    let pos = Pos(vector(16, 16));
+    let mut vel = Vel(vector(24, 0));
+    let mut wall_contact = WallContact::default();
+    system::physics_process(&pos, &mut vel, &Size(vector(16, 16)), &CollisionLayer::Normal, None, Some(&mut wall_contact), &tilemap);
+    assert!(vel.0 == vector(16, 0));
+    assert!(wall_contact.right && !wall_contact.left);
+  }
+}
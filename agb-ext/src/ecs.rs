@@ -1,4 +1,4 @@
-use agb::hash_map::HashMap;
+use agb_hashmap::HashMap;
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Entity {
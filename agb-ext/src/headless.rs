@@ -0,0 +1,230 @@
+//! Stand-ins for the agb types the engine drives, used in place of `agb` when its feature is off. They keep the
+//! same method signatures but draw nothing, so the game logic can run off-hardware.
+use core::marker::PhantomData;
+use core::ops::BitOr;
+use agb_fixnum::{Num, Vector2D};
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Button(u32);
+
+impl Button {
+  pub const A: Button = Button(1 << 0);
+  pub const B: Button = Button(1 << 1);
+  pub const SELECT: Button = Button(1 << 2);
+  pub const START: Button = Button(1 << 3);
+  pub const RIGHT: Button = Button(1 << 4);
+  pub const LEFT: Button = Button(1 << 5);
+  pub const UP: Button = Button(1 << 6);
+  pub const DOWN: Button = Button(1 << 7);
+  pub const R: Button = Button(1 << 8);
+  pub const L: Button = Button(1 << 9);
+
+  pub const fn empty() -> Self {
+    Button(0)
+  }
+
+  pub const fn bits(self) -> u32 {
+    self.0
+  }
+
+  pub const fn from_bits_truncate(bits: u32) -> Self {
+    Button(bits & 0x3FF)
+  }
+
+  pub const fn contains(self, other: Button) -> bool {
+    self.0 & other.0 == other.0
+  }
+}
+
+impl BitOr for Button {
+  type Output = Button;
+
+  fn bitor(self, rhs: Button) -> Button {
+    Button(self.0 | rhs.0)
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Tri {
+  Positive = 1,
+  Zero = 0,
+  Negative = -1,
+}
+
+impl From<(bool, bool)> for Tri {
+  fn from((negative, positive): (bool, bool)) -> Tri {
+    match (negative, positive) {
+      (false, true) => Tri::Positive,
+      (true, false) => Tri::Negative,
+      _ => Tri::Zero,
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Priority {
+  #[default]
+  P0,
+  P1,
+  P2,
+  P3,
+}
+
+pub struct Sprite;
+
+pub struct SpriteVram;
+
+pub struct Tag;
+
+impl Tag {
+  pub const fn sprite(&self, _idx: usize) -> &'static Sprite {
+    &Sprite
+  }
+}
+
+pub struct TagMap;
+
+impl TagMap {
+  pub const fn get(&self, _tag: &str) -> &'static Tag {
+    &Tag
+  }
+}
+
+pub struct Graphics;
+
+impl Graphics {
+  pub const HEADLESS: Graphics = Graphics;
+
+  pub const fn tags(&self) -> &'static TagMap {
+    &TagMap
+  }
+}
+
+#[derive(Default)]
+pub struct OamManaged<'gba>(PhantomData<&'gba ()>);
+
+impl<'gba> OamManaged<'gba> {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn object_sprite<'a>(&'a self, _sprite: &'static Sprite) -> Object<'a> {
+    Object(PhantomData)
+  }
+
+  pub fn sprite(&self, _sprite: &'static Sprite) -> SpriteVram {
+    SpriteVram
+  }
+
+  pub fn commit(&self) {}
+}
+
+pub struct Object<'a>(PhantomData<&'a ()>);
+
+impl<'a> Object<'a> {
+  pub fn set_sprite(&mut self, _sprite: SpriteVram) -> &mut Self {
+    self
+  }
+
+  pub fn set_position(&mut self, _position: Vector2D<i32>) -> &mut Self {
+    self
+  }
+
+  pub fn set_hflip(&mut self, _flip: bool) -> &mut Self {
+    self
+  }
+
+  pub fn set_vflip(&mut self, _flip: bool) -> &mut Self {
+    self
+  }
+
+  pub fn set_priority(&mut self, _priority: Priority) -> &mut Self {
+    self
+  }
+
+  pub fn show(&mut self) -> &mut Self {
+    self
+  }
+
+  pub fn hide(&mut self) -> &mut Self {
+    self
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BackgroundID(pub u8);
+
+#[derive(Clone, Copy, Debug)]
+pub enum Layer {
+  Top,
+  Bottom,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum BlendMode {
+  Off,
+  Normal,
+  FadeToWhite,
+  FadeToBlack,
+}
+
+#[derive(Default)]
+pub struct Blend<'gba>(PhantomData<&'gba ()>);
+
+impl<'gba> Blend<'gba> {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn reset_targets(&mut self) -> &mut Self {
+    self
+  }
+
+  pub fn set_blend_mode(&mut self, _blend_mode: BlendMode) -> &mut Self {
+    self
+  }
+
+  pub fn set_background_enable(&mut self, _layer: Layer, _background: BackgroundID, _enable: bool) -> &mut Self {
+    self
+  }
+
+  pub fn set_object_enable(&mut self, _layer: Layer, _enable: bool) -> &mut Self {
+    self
+  }
+
+  pub fn set_blend_weight(&mut self, _layer: Layer, _value: Num<u8, 4>) -> &mut Self {
+    self
+  }
+
+  pub fn commit(&self) {}
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Palette16 {
+  colours: [u16; 16],
+}
+
+impl Palette16 {
+  pub const fn new(colours: [u16; 16]) -> Self {
+    Palette16 { colours }
+  }
+
+  pub fn update_colour(&mut self, index: usize, colour: u16) {
+    self.colours[index] = colour;
+  }
+
+  pub fn colour(&self, index: usize) -> u16 {
+    self.colours[index]
+  }
+}
+
+#[derive(Default)]
+pub struct VRamManager;
+
+impl VRamManager {
+  pub fn new() -> Self {
+    VRamManager
+  }
+
+  pub fn set_background_palettes(&mut self, _palettes: &[Palette16]) {}
+}
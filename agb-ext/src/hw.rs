@@ -0,0 +1,29 @@
+//! The agb types the engine drives. Without the `agb` feature these are the no-op stand-ins from `headless`.
+#[cfg(feature = "agb")]
+pub use agb::{
+  display::{
+    object::{Graphics, OamManaged, Object, Sprite, Tag},
+    blend::{Blend, BlendMode, Layer},
+    tiled::{BackgroundID, VRamManager},
+    palette16::Palette16,
+    Priority,
+  },
+  input::{Button, Tri},
+};
+
+#[cfg(not(feature = "agb"))]
+pub use crate::headless::*;
+
+/// `agb::include_aseprite!`, or empty graphics without the `agb` feature.
+#[cfg(feature = "agb")]
+#[macro_export]
+macro_rules! include_aseprite {
+  ($($args:tt)*) => { agb::include_aseprite!($($args)*) };
+}
+
+/// `agb::include_aseprite!`, or empty graphics without the `agb` feature.
+#[cfg(not(feature = "agb"))]
+#[macro_export]
+macro_rules! include_aseprite {
+  ($($args:tt)*) => { &$crate::hw::Graphics::HEADLESS };
+}
@@ -0,0 +1,365 @@
+//! Per-frame input that doesn't come straight from the keypad. The game reads an `InputState` fed with whatever
+//! buttons were held each frame, which can be live input, a recording being played back, or a test script. Systems
+//! that shouldn't care which buttons do what query an `ActionState` instead, through the game's remappable `Bindings`.
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use crate::hw::{Button, Tri};
+use crate::save::{ByteWriter, ByteReader, SaveError};
+
+/// The buttons held this frame and the last, with the same queries as agb's `ButtonController`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct InputState {
+  previous: Button,
+  current: Button,
+}
+
+impl Default for InputState {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl InputState {
+  pub const fn new() -> Self {
+    InputState { previous: Button::empty(), current: Button::empty() }
+  }
+
+  pub fn update(&mut self, held: Button) {
+    self.previous = self.current;
+    self.current = held;
+  }
+
+  pub fn held(&self) -> Button {
+    self.current
+  }
+
+  pub fn x_tri(&self) -> Tri {
+    (self.is_pressed(Button::LEFT), self.is_pressed(Button::RIGHT)).into()
+  }
+
+  pub fn y_tri(&self) -> Tri {
+    (self.is_pressed(Button::UP), self.is_pressed(Button::DOWN)).into()
+  }
+
+  pub fn is_pressed(&self, keys: Button) -> bool {
+    self.current.bits() & keys.bits() != 0
+  }
+
+  pub fn is_released(&self, keys: Button) -> bool {
+    !self.is_pressed(keys)
+  }
+
+  pub fn is_just_pressed(&self, keys: Button) -> bool {
+    self.current.bits() & keys.bits() != 0 && self.previous.bits() & keys.bits() == 0
+  }
+
+  pub fn is_just_released(&self, keys: Button) -> bool {
+    self.current.bits() & keys.bits() == 0 && self.previous.bits() & keys.bits() != 0
+  }
+}
+
+/// The buttons `controller` saw on its last `update`.
+#[cfg(feature = "agb")]
+pub fn held_buttons(controller: &agb::input::ButtonController) -> Button {
+  const ALL_BUTTONS: [Button; 10] = [
+    Button::A, Button::B, Button::SELECT, Button::START,
+    Button::RIGHT, Button::LEFT, Button::UP, Button::DOWN,
+    Button::R, Button::L,
+  ];
+  ALL_BUTTONS.iter()
+    .filter(|&&button| controller.is_pressed(button))
+    .fold(Button::empty(), |held, &button| held | button)
+}
+
+/// A game's set of actions, each bound to a chord of buttons by `Bindings`. At most 32 actions are supported.
+pub trait Action: Copy + PartialEq + Eq + core::fmt::Debug + 'static {
+  /// Every action, in the order `index` numbers them.
+  const ALL: &'static [Self];
+
+  fn index(self) -> usize;
+
+  fn default_chord(self) -> Button;
+}
+
+/// The chord of buttons each action is bound to. An action is held while every button in its chord is held.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Bindings<A: Action> {
+  chords: Vec<Button>,
+  _action: PhantomData<A>,
+}
+
+impl<A: Action> Default for Bindings<A> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<A: Action> Bindings<A> {
+  pub fn new() -> Self {
+    debug_assert!(A::ALL.len() <= 32);
+    Bindings { chords: A::ALL.iter().map(|action| action.default_chord()).collect(), _action: PhantomData }
+  }
+
+  pub fn chord(&self, action: A) -> Button {
+    self.chords[action.index()]
+  }
+
+  /// Binds `action` to `chord`, or unbinds it if `chord` is empty.
+  pub fn bind(&mut self, action: A, chord: Button) {
+    self.chords[action.index()] = chord;
+  }
+
+  /// The actions held with `held` down, one bit per action index. An action whose chord is part of a larger held
+  /// chord is left out, so binding one action to R+B and another to B doesn't trigger both.
+  pub fn held_actions(&self, held: Button) -> u32 {
+    let is_held = |chord: Button| chord != Button::empty() && held.contains(chord);
+    self.chords.iter()
+      .enumerate()
+      .filter(|&(_, &chord)| is_held(chord))
+      .filter(|&(_, &chord)| !self.chords.iter().any(|&other| other != chord && is_held(other) && other.contains(chord)))
+      .fold(0, |actions, (index, _)| actions | 1 << index)
+  }
+}
+
+/// The actions held this frame and the last, queried by action the same way `InputState` is by button.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ActionState<A: Action> {
+  previous: u32,
+  current: u32,
+  _action: PhantomData<A>,
+}
+
+impl<A: Action> Default for ActionState<A> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<A: Action> ActionState<A> {
+  pub const fn new() -> Self {
+    ActionState { previous: 0, current: 0, _action: PhantomData }
+  }
+
+  pub fn update(&mut self, bindings: &Bindings<A>, held: Button) {
+    self.previous = self.current;
+    self.current = bindings.held_actions(held);
+  }
+
+  /// Which way a pair of opposing actions points, like `InputState::x_tri`.
+  pub fn tri(&self, negative: A, positive: A) -> Tri {
+    (self.is_pressed(negative), self.is_pressed(positive)).into()
+  }
+
+  pub fn is_pressed(&self, action: A) -> bool {
+    self.current & 1 << action.index() != 0
+  }
+
+  pub fn is_released(&self, action: A) -> bool {
+    !self.is_pressed(action)
+  }
+
+  pub fn is_just_pressed(&self, action: A) -> bool {
+    self.is_pressed(action) && self.previous & 1 << action.index() == 0
+  }
+
+  pub fn is_just_released(&self, action: A) -> bool {
+    self.is_released(action) && self.previous & 1 << action.index() != 0
+  }
+}
+
+/// Held buttons for a run of frames, stored as runs of identical frames since input rarely changes frame to frame.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct InputRecording {
+  runs: Vec<(u16, u16)>,
+}
+
+impl InputRecording {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn push(&mut self, held: Button) {
+    let bits = held.bits() as u16;
+    match self.runs.last_mut() {
+      Some((frames, run_bits)) if *run_bits == bits && *frames < u16::MAX => *frames += 1,
+      _ => self.runs.push((1, bits)),
+    }
+  }
+
+  pub fn clear(&mut self) {
+    self.runs.clear();
+  }
+
+  pub fn frames(&self) -> u32 {
+    self.runs.iter().map(|&(frames, _)| frames as u32).sum()
+  }
+
+  pub fn encoded_len(&self) -> usize {
+    4 + self.runs.len() * 4
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = Button> + '_ {
+    self.runs.iter()
+      .flat_map(|&(frames, bits)| core::iter::repeat(Button::from_bits_truncate(bits as u32)).take(frames as usize))
+  }
+
+  pub fn encode(&self, writer: &mut ByteWriter) {
+    writer.u32(self.runs.len() as u32);
+    for &(frames, bits) in &self.runs {
+      writer.u16(frames).u16(bits);
+    }
+  }
+
+  pub fn decode(reader: &mut ByteReader) -> Result<Self, SaveError> {
+    let run_count = reader.u32()? as usize;
+    if run_count * 4 > reader.remaining() {
+      return Err(SaveError::Corrupt);
+    }
+    let mut runs = Vec::with_capacity(run_count);
+    for _ in 0..run_count {
+      let frames = reader.u16()?;
+      let bits = reader.u16()?;
+      if frames == 0 {
+        return Err(SaveError::Corrupt);
+      }
+      runs.push((frames, bits));
+    }
+    Ok(InputRecording { runs })
+  }
+}
+
+/// Feeds a recording back one frame at a time, ending once every recorded frame has been played.
+pub struct InputPlayback {
+  recording: InputRecording,
+  run: usize,
+  frame: u16,
+}
+
+impl InputPlayback {
+  pub fn new(recording: InputRecording) -> Self {
+    InputPlayback { recording, run: 0, frame: 0 }
+  }
+}
+
+impl Iterator for InputPlayback {
+  type Item = Button;
+
+  fn next(&mut self) -> Option<Button> {
+    let &(frames, bits) = self.recording.runs.get(self.run)?;
+    self.frame += 1;
+    if self.frame == frames {
+      self.run += 1;
+      self.frame = 0;
+    }
+    Some(Button::from_bits_truncate(bits as u32))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use alloc::vec;
+
+  fn record(frames: &[Button]) -> InputRecording {
+    let mut recording = InputRecording::new();
+    for &held in frames {
+      recording.push(held);
+    }
+    recording
+  }
+
+  #[test]
+  fn just_pressed_only_on_the_first_held_frame() {
+    let mut input = InputState::new();
+    input.update(Button::A);
+    assert!(input.is_just_pressed(Button::A));
+    input.update(Button::A | Button::LEFT);
+    assert!(!input.is_just_pressed(Button::A));
+    assert!(input.is_pressed(Button::A));
+    assert_eq!(input.x_tri(), Tri::Negative);
+    input.update(Button::empty());
+    assert!(input.is_just_released(Button::A | Button::LEFT));
+  }
+
+  #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+  enum TestAction {
+    Jump,
+    Dash,
+    Pause,
+  }
+
+  impl Action for TestAction {
+    const ALL: &'static [Self] = &[TestAction::Jump, TestAction::Dash, TestAction::Pause];
+
+    fn index(self) -> usize {
+      self as usize
+    }
+
+    fn default_chord(self) -> Button {
+      match self {
+        TestAction::Jump => Button::B,
+        TestAction::Dash => Button::R | Button::B,
+        TestAction::Pause => Button::START,
+      }
+    }
+  }
+
+  #[test]
+  fn larger_chords_hide_the_actions_they_contain() {
+    let bindings = Bindings::<TestAction>::new();
+    let mut actions = ActionState::new();
+    actions.update(&bindings, Button::B);
+    assert!(actions.is_just_pressed(TestAction::Jump));
+    assert!(actions.is_released(TestAction::Dash));
+
+    actions.update(&bindings, Button::B | Button::R | Button::START);
+    assert!(actions.is_just_pressed(TestAction::Dash));
+    assert!(actions.is_just_released(TestAction::Jump));
+    assert!(actions.is_just_pressed(TestAction::Pause));
+  }
+
+  #[test]
+  fn remapped_actions_follow_their_new_chord() {
+    let mut bindings = Bindings::<TestAction>::new();
+    bindings.bind(TestAction::Jump, Button::A);
+    bindings.bind(TestAction::Pause, Button::empty());
+    assert_eq!(bindings.held_actions(Button::A), 1 << TestAction::Jump.index());
+    assert_eq!(bindings.held_actions(Button::B | Button::START), 0);
+  }
+
+  #[test]
+  fn recording_merges_repeated_frames() {
+    let recording = record(&[Button::A, Button::A, Button::A, Button::B, Button::A]);
+    assert_eq!(recording.runs, vec![(3, Button::A.bits() as u16), (1, Button::B.bits() as u16), (1, Button::A.bits() as u16)]);
+    assert_eq!(recording.frames(), 5);
+  }
+
+  #[test]
+  fn long_runs_split_rather_than_overflow() {
+    let mut recording = InputRecording::new();
+    for _ in 0..u16::MAX as u32 + 2 {
+      recording.push(Button::RIGHT);
+    }
+    assert_eq!(recording.runs.len(), 2);
+    assert_eq!(recording.frames(), u16::MAX as u32 + 2);
+  }
+
+  #[test]
+  fn playback_feeds_back_every_recorded_frame() {
+    let frames = [Button::empty(), Button::A, Button::A | Button::RIGHT, Button::RIGHT, Button::RIGHT, Button::empty()];
+    let played: Vec<Button> = InputPlayback::new(record(&frames)).collect();
+    assert_eq!(played, frames);
+    assert_eq!(record(&frames).iter().collect::<Vec<_>>(), frames);
+  }
+
+  #[test]
+  fn encoding_round_trips() {
+    let recording = record(&[Button::L, Button::L, Button::UP | Button::B]);
+    let mut writer = ByteWriter::new();
+    recording.encode(&mut writer);
+    let bytes = writer.finish();
+    assert_eq!(bytes.len(), recording.encoded_len());
+    assert_eq!(InputRecording::decode(&mut ByteReader::new(&bytes)), Ok(recording));
+    assert_eq!(InputRecording::decode(&mut ByteReader::new(&bytes[..bytes.len() - 1])), Err(SaveError::Corrupt));
+  }
+}
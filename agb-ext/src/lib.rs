@@ -1,13 +1,23 @@
 #![feature(const_trait_impl)]
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
+pub mod hw;
+#[cfg(not(feature = "agb"))]
+pub mod headless;
 pub mod anim;
+#[cfg(feature = "agb")]
 pub mod tiles;
+pub mod rle;
+pub mod overlay;
 pub mod math;
 pub mod camera;
+#[cfg(feature = "agb")]
 pub mod sound;
 pub mod collision;
 pub mod ecs;
 pub mod blend;
+pub mod save;
+pub mod input;
+pub mod palette;
@@ -1,5 +1,5 @@
 use core::convert::From;
-use agb::fixnum::{
+use agb_fixnum::{
   Num,
   FixedWidthUnsignedInteger,
 };
@@ -9,6 +9,14 @@ pub type PosNum = Num<i32, 8>;
 pub const ZERO: PosNum = PosNum::from_raw(0);
 pub const MIN_INC: PosNum = PosNum::from_raw(1);
 
+/// GBA frames per second.
+pub const FRAME_RATE: u32 = 60;
+
+/// Converts a millisecond duration, as Tiled and Aseprite store them, to the nearest whole number of frames.
+pub const fn frames_from_ms(ms: u32) -> u32 {
+  (ms * FRAME_RATE + 500) / 1000
+}
+
 pub const fn const_num_u32<const Bits: usize>(ipart: u32, fpart: u32) -> Num<u32, Bits> {
   let places = {
     let mut places = 0;
@@ -60,3 +68,29 @@ pub const fn const_num_i32<const Bits: usize>(ipart: i32, fpart: i32) -> Num<i32
 
   Num::<i32, Bits>::from_raw((shifted_ipart + shifted_fpart) << (Bits - fshift))
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn const_num_matches_decimal_fraction() {
+    assert_eq!(const_num_i32::<8>(0, 5), PosNum::from_raw(128));
+    assert_eq!(const_num_i32::<8>(2, 25), PosNum::from_raw(576));
+    assert_eq!(const_num_i32::<8>(3, 0), PosNum::new(3));
+    assert_eq!(const_num_u32::<8>(7, 125), Num::<u32, 8>::from_raw(1824));
+  }
+
+  #[test]
+  fn frames_from_ms_rounds_to_nearest() {
+    assert_eq!(frames_from_ms(83), 5);
+    assert_eq!(frames_from_ms(500), 30);
+    assert_eq!(frames_from_ms(1000), 60);
+    assert_eq!(frames_from_ms(8), 0);
+  }
+
+  #[test]
+  fn const_num_truncates_unrepresentable_fraction() {
+    assert_eq!(const_num_i32::<8>(0, 1), PosNum::from_raw(25));
+  }
+}
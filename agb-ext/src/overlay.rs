@@ -0,0 +1,136 @@
+use alloc::{vec::Vec, collections::BTreeMap};
+use core::cell::RefCell;
+use agb_fixnum::Vector2D;
+use crate::collision::{CollideTileType, CollideTilemap};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FlipTile<I> {
+  N(I),
+  X(I),
+  Y(I),
+  XY(I),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum TilemapLayer {
+  Background,
+  Primary,
+  Foreground,
+}
+
+/// A runtime change to one metatile of a layer, optionally replacing the collision at the same spot.
+#[derive(Clone, Copy, PartialEq)]
+pub struct TileEdit {
+  pub layer: TilemapLayer,
+  pub pos: Vector2D<i32>,
+  pub metatile: FlipTile<u16>,
+  pub collision: Option<CollideTileType>,
+}
+
+/// Copy-on-write edits layered over a `Tilemap`'s static data.
+pub struct TileOverlay {
+  edits: RefCell<BTreeMap<(TilemapLayer, i32, i32), TileEdit>>,
+  dirty: RefCell<Vec<TileEdit>>,
+}
+
+impl<I> FlipTile<I> {
+  pub fn idx(self) -> I {
+    match self {
+      Self::N(idx) => idx,
+      Self::X(idx) => idx,
+      Self::Y(idx) => idx,
+      Self::XY(idx) => idx,
+    }
+  }
+
+  pub fn x_flipped(self) -> bool {
+    match self {
+      Self::X(_) => true,
+      Self::XY(_) => true,
+      _ => false,
+    }
+  }
+
+  pub fn y_flipped(self) -> bool {
+    match self {
+      Self::Y(_) => true,
+      Self::XY(_) => true,
+      _ => false,
+    }
+  }
+
+  pub fn flip_x(self) -> Self {
+    match self {
+      Self::N(idx) => Self::X(idx),
+      Self::X(idx) => Self::N(idx),
+      Self::Y(idx) => Self::XY(idx),
+      Self::XY(idx) => Self::Y(idx),
+    }
+  }
+
+  pub fn flip_y(self) -> Self {
+    match self {
+      Self::N(idx) => Self::Y(idx),
+      Self::Y(idx) => Self::N(idx),
+      Self::X(idx) => Self::XY(idx),
+      Self::XY(idx) => Self::X(idx),
+    }
+  }
+
+  pub fn from_flip_bits(idx: I, flip: u8) -> Option<Self> {
+    match flip {
+      0 => Some(Self::N(idx)),
+      1 => Some(Self::X(idx)),
+      2 => Some(Self::Y(idx)),
+      3 => Some(Self::XY(idx)),
+      _ => None,
+    }
+  }
+
+  pub fn flip_bits(self) -> u8 {
+    match self {
+      Self::N(_) => 0,
+      Self::X(_) => 1,
+      Self::Y(_) => 2,
+      Self::XY(_) => 3,
+    }
+  }
+}
+
+impl TileOverlay {
+  pub fn new() -> Self {
+    TileOverlay {
+      edits: RefCell::new(BTreeMap::new()),
+      dirty: RefCell::new(Vec::new()),
+    }
+  }
+
+  pub fn apply(&self, collide_tilemap: &mut CollideTilemap, edit: TileEdit) {
+    if let Some(collision) = edit.collision {
+      collide_tilemap.set_tile(edit.pos, collision);
+    }
+    self.edits.borrow_mut().insert((edit.layer, edit.pos.x, edit.pos.y), edit);
+    self.dirty.borrow_mut().push(edit);
+  }
+
+  /// Drops every edit, restoring the static map data on the next redraw.
+  pub fn clear(&self, collide_tilemap: &mut CollideTilemap) {
+    let edits = core::mem::take(&mut *self.edits.borrow_mut());
+    self.dirty.borrow_mut().extend(edits.into_values());
+    collide_tilemap.clear_overrides();
+  }
+
+  pub fn edits(&self) -> Vec<TileEdit> {
+    self.edits.borrow().values().copied().collect()
+  }
+
+  #[cfg(feature = "agb")]
+  pub(crate) fn metatile(&self, layer: TilemapLayer, pos: Vector2D<i32>) -> Option<FlipTile<u16>> {
+    self.edits.borrow().get(&(layer, pos.x, pos.y)).map(|edit| edit.metatile)
+  }
+
+  #[cfg(feature = "agb")]
+  pub(crate) fn take_dirty(&self) -> Vec<TileEdit> {
+    core::mem::take(&mut *self.dirty.borrow_mut())
+  }
+}
@@ -0,0 +1,115 @@
+use alloc::vec::Vec;
+use crate::hw::{Palette16, VRamManager};
+
+pub const MAX_FADE: u8 = 16;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FadeColour {
+  Black,
+  White,
+}
+
+/// Owns the background palettes so they can be swapped and faded at runtime.
+/// Changes are written to palette RAM on `commit`.
+pub struct PaletteFader {
+  palettes: Vec<Palette16>,
+  colour: FadeColour,
+  fade: u8,
+  dirty: bool,
+}
+
+impl PaletteFader {
+  pub fn new(palettes: &[Palette16]) -> Self {
+    PaletteFader {
+      palettes: palettes.to_vec(),
+      colour: FadeColour::Black,
+      fade: 0,
+      dirty: true,
+    }
+  }
+
+  pub fn palettes(&self) -> &[Palette16] {
+    &self.palettes
+  }
+
+  pub fn set_palettes(&mut self, palettes: &[Palette16]) {
+    self.palettes.clear();
+    self.palettes.extend_from_slice(palettes);
+    self.dirty = true;
+  }
+
+  pub fn set_palette(&mut self, index: usize, palette: &Palette16) {
+    if index >= self.palettes.len() {
+      self.palettes.resize(index + 1, Palette16::new([0; 16]));
+    }
+    self.palettes[index] = palette.clone();
+    self.dirty = true;
+  }
+
+  pub fn get_fade(&self) -> (FadeColour, u8) {
+    (self.colour, self.fade)
+  }
+
+  pub fn set_fade(&mut self, colour: FadeColour, fade: u8) {
+    let fade = fade.min(MAX_FADE);
+    if (colour, fade) != (self.colour, self.fade) {
+      self.colour = colour;
+      self.fade = fade;
+      self.dirty = true;
+    }
+  }
+
+  pub fn min_inc_fade(&mut self, colour: FadeColour) {
+    if self.colour != colour {
+      self.set_fade(colour, 1);
+    } else {
+      self.set_fade(colour, self.fade + 1);
+    }
+  }
+
+  pub fn min_dec_fade(&mut self) {
+    self.set_fade(self.colour, self.fade.saturating_sub(1));
+  }
+
+  pub fn is_faded_out(&self) -> bool {
+    self.fade == MAX_FADE
+  }
+
+  pub fn is_faded_in(&self) -> bool {
+    self.fade == 0
+  }
+
+  pub fn commit(&mut self, vram: &mut VRamManager) {
+    if !self.dirty {
+      return;
+    }
+    self.dirty = false;
+    if self.fade == 0 {
+      vram.set_background_palettes(&self.palettes);
+    } else {
+      let faded: Vec<Palette16> = self.palettes.iter()
+        .map(|palette| {
+          let mut faded = palette.clone();
+          for colour_index in 0..16 {
+            faded.update_colour(colour_index, fade_colour(palette.colour(colour_index), self.colour, self.fade));
+          }
+          faded
+        })
+        .collect();
+      vram.set_background_palettes(&faded);
+    }
+  }
+}
+
+fn fade_colour(colour: u16, target: FadeColour, fade: u8) -> u16 {
+  let target = match target {
+    FadeColour::Black => 0,
+    FadeColour::White => 0x1F,
+  };
+  let fade = fade as i32;
+  [0, 5, 10].iter().fold(0, |faded, shift| {
+    let channel = (colour >> shift & 0x1F) as i32;
+    let channel = channel + (target - channel) * fade / MAX_FADE as i32;
+    faded | (channel as u16) << shift
+  })
+}
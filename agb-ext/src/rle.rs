@@ -0,0 +1,97 @@
+/// Run-length encoded map layer. Each row is a series of runs, each a length byte followed by
+/// `value_size` bytes of value, and `rows` holds the offset each row starts at so a lookup only
+/// has to walk the runs of a single row.
+#[derive(Clone, Copy)]
+pub struct RleLayer {
+  data: &'static [u8],
+  rows: &'static [u32],
+  value_size: usize,
+}
+
+pub struct RleRow {
+  data: &'static [u8],
+  value_size: usize,
+  run_left: u8,
+  value: &'static [u8],
+}
+
+impl RleLayer {
+  pub const fn new(data: &'static [u8], rows: &'static [u32], value_size: usize) -> Self {
+    RleLayer { data, rows, value_size }
+  }
+
+  pub const fn height(&self) -> usize {
+    self.rows.len()
+  }
+
+  /// Bytes per cell value.
+  pub const fn value_size(&self) -> usize {
+    self.value_size
+  }
+
+  /// Decodes one row cell by cell.
+  pub fn row(&self, y: usize) -> RleRow {
+    let end = self.rows.get(y + 1).map_or(self.data.len(), |&end| end as usize);
+    RleRow {
+      data: &self.data[self.rows[y] as usize..end],
+      value_size: self.value_size,
+      run_left: 0,
+      value: &[],
+    }
+  }
+
+  pub fn get(&self, x: usize, y: usize) -> &'static [u8] {
+    let mut offset = self.rows[y] as usize;
+    let mut x = x;
+    loop {
+      let run = self.data[offset] as usize;
+      if x < run {
+        return &self.data[offset + 1..offset + 1 + self.value_size];
+      }
+      x -= run;
+      offset += 1 + self.value_size;
+    }
+  }
+}
+
+impl Iterator for RleRow {
+  type Item = &'static [u8];
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.run_left == 0 {
+      let (&run, rest) = self.data.split_first()?;
+      if rest.len() < self.value_size {
+        return None;
+      }
+      self.run_left = run;
+      (self.value, self.data) = rest.split_at(self.value_size);
+    }
+    self.run_left -= 1;
+    Some(self.value)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // 3x2 layer of 2-byte values: row 0 is 2 x [1, 0] then [2, 0], row 1 is 3 x [3, 1]
+  const DATA: &[u8] = &[2, 1, 0, 1, 2, 0, 3, 3, 1];
+  const LAYER: RleLayer = RleLayer::new(DATA, &[0, 6], 2);
+
+  #[test]
+  fn get_finds_the_run_containing_a_cell() {
+    assert_eq!(LAYER.height(), 2);
+    assert_eq!(LAYER.get(0, 0), &[1, 0]);
+    assert_eq!(LAYER.get(1, 0), &[1, 0]);
+    assert_eq!(LAYER.get(2, 0), &[2, 0]);
+    assert_eq!(LAYER.get(2, 1), &[3, 1]);
+  }
+
+  #[test]
+  fn row_decodes_every_cell() {
+    let row: Vec<&[u8]> = LAYER.row(0).collect();
+    assert_eq!(row, [&[1, 0], &[1, 0], &[2, 0]]);
+    assert_eq!(LAYER.row(1).count(), 3);
+  }
+}
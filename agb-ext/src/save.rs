@@ -0,0 +1,436 @@
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+const MAGIC: [u8; 4] = *b"GGSV";
+const HEADER_LEN: usize = 16;
+const COPIES_PER_SLOT: usize = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SaveError {
+  Media,
+  OutOfBounds,
+  Empty,
+  Corrupt,
+  UnsupportedVersion(u16),
+  TooLarge,
+}
+
+pub trait SaveMedia {
+  fn len(&self) -> usize;
+  fn read(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), SaveError>;
+  fn write(&mut self, offset: usize, buffer: &[u8]) -> Result<(), SaveError>;
+}
+
+pub struct BufferMedia<'b>(pub &'b mut [u8]);
+
+pub struct SaveSlots {
+  offset: usize,
+  slot_count: usize,
+  slot_size: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct RecordHeader {
+  version: u16,
+  len: u16,
+  sequence: u32,
+  checksum: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SaveRecord {
+  pub version: u16,
+  pub payload: Vec<u8>,
+}
+
+#[cfg(feature = "agb")]
+impl SaveMedia for agb::save::SaveData {
+  fn len(&self) -> usize {
+    agb::save::SaveData::len(self)
+  }
+
+  fn read(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), SaveError> {
+    agb::save::SaveData::read(self, offset, buffer).map_err(|_| SaveError::Media)
+  }
+
+  fn write(&mut self, offset: usize, buffer: &[u8]) -> Result<(), SaveError> {
+    let mut block = self.prepare_write(offset..offset + buffer.len()).map_err(|_| SaveError::Media)?;
+    block.write_and_verify(offset, buffer).map_err(|_| SaveError::Media)
+  }
+}
+
+impl<'b> SaveMedia for BufferMedia<'b> {
+  fn len(&self) -> usize {
+    self.0.len()
+  }
+
+  fn read(&mut self, offset: usize, buffer: &mut [u8]) -> Result<(), SaveError> {
+    let src = self.0.get(offset..offset + buffer.len()).ok_or(SaveError::OutOfBounds)?;
+    buffer.copy_from_slice(src);
+    Ok(())
+  }
+
+  fn write(&mut self, offset: usize, buffer: &[u8]) -> Result<(), SaveError> {
+    let dst = self.0.get_mut(offset..offset + buffer.len()).ok_or(SaveError::OutOfBounds)?;
+    dst.copy_from_slice(buffer);
+    Ok(())
+  }
+}
+
+impl RecordHeader {
+  fn to_bytes(self) -> [u8; HEADER_LEN] {
+    let mut bytes = [0; HEADER_LEN];
+    bytes[0..4].copy_from_slice(&MAGIC);
+    bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+    bytes[6..8].copy_from_slice(&self.len.to_le_bytes());
+    bytes[8..12].copy_from_slice(&self.sequence.to_le_bytes());
+    bytes[12..16].copy_from_slice(&self.checksum.to_le_bytes());
+    bytes
+  }
+
+  fn from_bytes(bytes: &[u8; HEADER_LEN]) -> Option<Self> {
+    if bytes[0..4] != MAGIC {
+      return None;
+    }
+    Some(Self {
+      version: u16::from_le_bytes([bytes[4], bytes[5]]),
+      len: u16::from_le_bytes([bytes[6], bytes[7]]),
+      sequence: u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+      checksum: u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+    })
+  }
+}
+
+impl SaveSlots {
+  pub const fn new(slot_count: usize, slot_size: usize) -> Self {
+    Self { offset: 0, slot_count, slot_size }
+  }
+
+  /// Places the slots `offset` bytes into the save media, so several sets of slots can share it.
+  pub const fn starting_at(self, offset: usize) -> Self {
+    Self { offset, ..self }
+  }
+
+  /// The offset of the first byte after the last slot.
+  pub const fn end(&self) -> usize {
+    self.offset + self.slot_count * self.slot_size
+  }
+
+  pub fn slot_count(&self) -> usize {
+    self.slot_count
+  }
+
+  pub fn max_payload_len(&self) -> usize {
+    (self.slot_size / COPIES_PER_SLOT).saturating_sub(HEADER_LEN).min(u16::MAX as usize)
+  }
+
+  pub fn load(&self, media: &mut (impl SaveMedia + ?Sized), slot: usize) -> Result<SaveRecord, SaveError> {
+    let mut newest: Option<(RecordHeader, Vec<u8>)> = None;
+    let mut any_written = false;
+    for copy in 0..COPIES_PER_SLOT {
+      match self.read_copy(media, slot, copy)? {
+        CopyState::Valid(header, payload) => {
+          any_written = true;
+          if newest.as_ref().map_or(true, |(newest, _)| is_newer(header.sequence, newest.sequence)) {
+            newest = Some((header, payload));
+          }
+        }
+        CopyState::Corrupt => any_written = true,
+        CopyState::Empty => {}
+      }
+    }
+
+    match newest {
+      Some((header, payload)) => Ok(SaveRecord { version: header.version, payload }),
+      None if any_written => Err(SaveError::Corrupt),
+      None => Err(SaveError::Empty),
+    }
+  }
+
+  pub fn store(&self, media: &mut (impl SaveMedia + ?Sized), slot: usize, version: u16, payload: &[u8]) -> Result<(), SaveError> {
+    if payload.len() > self.max_payload_len() {
+      return Err(SaveError::TooLarge);
+    }
+
+    // Overwrite the older (or damaged) copy so a failed write always leaves the previous save intact.
+    let mut newest: Option<(usize, u32)> = None;
+    let mut unused = None;
+    for copy in 0..COPIES_PER_SLOT {
+      match self.read_copy(media, slot, copy)? {
+        CopyState::Valid(header, _) => {
+          if newest.map_or(true, |(_, sequence)| is_newer(header.sequence, sequence)) {
+            newest = Some((copy, header.sequence));
+          }
+        }
+        _ => if unused.is_none() {
+          unused = Some(copy);
+        }
+      }
+    }
+    let (target, sequence) = match newest {
+      Some((copy, sequence)) => ((copy + 1) % COPIES_PER_SLOT, sequence.wrapping_add(1)),
+      None => (unused.unwrap_or(0), 0),
+    };
+
+    let mut header = RecordHeader {
+      version,
+      len: payload.len() as u16,
+      sequence,
+      checksum: 0,
+    };
+    header.checksum = record_checksum(&header, payload);
+    let range = self.copy_range(media, slot, target)?;
+    let mut record = Vec::with_capacity(HEADER_LEN + payload.len());
+    record.extend_from_slice(&header.to_bytes());
+    record.extend_from_slice(payload);
+    media.write(range.start, &record)
+  }
+
+  pub fn erase(&self, media: &mut (impl SaveMedia + ?Sized), slot: usize) -> Result<(), SaveError> {
+    for copy in 0..COPIES_PER_SLOT {
+      let range = self.copy_range(media, slot, copy)?;
+      media.write(range.start, &[0; HEADER_LEN])?;
+    }
+    Ok(())
+  }
+
+  fn copy_range(&self, media: &(impl SaveMedia + ?Sized), slot: usize, copy: usize) -> Result<Range<usize>, SaveError> {
+    if slot >= self.slot_count {
+      return Err(SaveError::OutOfBounds);
+    }
+    let copy_size = self.slot_size / COPIES_PER_SLOT;
+    let start = self.offset + slot * self.slot_size + copy * copy_size;
+    if start + copy_size > media.len() {
+      return Err(SaveError::OutOfBounds);
+    }
+    Ok(start..start + copy_size)
+  }
+
+  fn read_copy(&self, media: &mut (impl SaveMedia + ?Sized), slot: usize, copy: usize) -> Result<CopyState, SaveError> {
+    let range = self.copy_range(media, slot, copy)?;
+    let mut header_bytes = [0; HEADER_LEN];
+    media.read(range.start, &mut header_bytes)?;
+    let header = match RecordHeader::from_bytes(&header_bytes) {
+      Some(header) => header,
+      None => return Ok(CopyState::Empty),
+    };
+    if header.len as usize > range.len() - HEADER_LEN {
+      return Ok(CopyState::Corrupt);
+    }
+
+    let mut payload = vec![0; header.len as usize];
+    media.read(range.start + HEADER_LEN, &mut payload)?;
+    if record_checksum(&header, &payload) != header.checksum {
+      return Ok(CopyState::Corrupt);
+    }
+    Ok(CopyState::Valid(header, payload))
+  }
+}
+
+enum CopyState {
+  Empty,
+  Corrupt,
+  Valid(RecordHeader, Vec<u8>),
+}
+
+fn is_newer(sequence: u32, than: u32) -> bool {
+  (sequence.wrapping_sub(than) as i32) > 0
+}
+
+fn record_checksum(header: &RecordHeader, payload: &[u8]) -> u32 {
+  let header_bytes = header.to_bytes();
+  !crc32(crc32(0xFFFF_FFFF, &header_bytes[4..12]), payload)
+}
+
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+  for byte in data {
+    crc ^= *byte as u32;
+    for _ in 0..8 {
+      let mask = (crc & 1).wrapping_neg();
+      crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+    }
+  }
+  crc
+}
+
+#[derive(Default)]
+pub struct ByteWriter {
+  bytes: Vec<u8>,
+}
+
+pub struct ByteReader<'b> {
+  bytes: &'b [u8],
+  pos: usize,
+}
+
+impl ByteWriter {
+  pub fn new() -> Self {
+    Self { bytes: Vec::new() }
+  }
+
+  pub fn u8(&mut self, value: u8) -> &mut Self {
+    self.bytes.push(value);
+    self
+  }
+
+  pub fn u16(&mut self, value: u16) -> &mut Self {
+    self.bytes.extend_from_slice(&value.to_le_bytes());
+    self
+  }
+
+  pub fn u32(&mut self, value: u32) -> &mut Self {
+    self.bytes.extend_from_slice(&value.to_le_bytes());
+    self
+  }
+
+  pub fn i32(&mut self, value: i32) -> &mut Self {
+    self.bytes.extend_from_slice(&value.to_le_bytes());
+    self
+  }
+
+  pub fn finish(self) -> Vec<u8> {
+    self.bytes
+  }
+}
+
+impl<'b> ByteReader<'b> {
+  pub fn new(bytes: &'b [u8]) -> Self {
+    Self { bytes, pos: 0 }
+  }
+
+  fn take<const N: usize>(&mut self) -> Result<[u8; N], SaveError> {
+    let src = self.bytes.get(self.pos..self.pos + N).ok_or(SaveError::Corrupt)?;
+    self.pos += N;
+    let mut out = [0; N];
+    out.copy_from_slice(src);
+    Ok(out)
+  }
+
+  pub fn u8(&mut self) -> Result<u8, SaveError> {
+    Ok(self.take::<1>()?[0])
+  }
+
+  pub fn u16(&mut self) -> Result<u16, SaveError> {
+    Ok(u16::from_le_bytes(self.take()?))
+  }
+
+  pub fn u32(&mut self) -> Result<u32, SaveError> {
+    Ok(u32::from_le_bytes(self.take()?))
+  }
+
+  pub fn i32(&mut self) -> Result<i32, SaveError> {
+    Ok(i32::from_le_bytes(self.take()?))
+  }
+
+  pub fn remaining(&self) -> usize {
+    self.bytes.len() - self.pos
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SLOTS: SaveSlots = SaveSlots::new(3, 128);
+
+  #[test]
+  fn empty_media_has_no_save() {
+    let mut data = [0xFF; 384];
+    let mut media = BufferMedia(&mut data);
+    assert_eq!(SLOTS.load(&mut media, 0), Err(SaveError::Empty));
+  }
+
+  #[test]
+  fn round_trips_each_slot_independently() {
+    let mut data = [0xFF; 384];
+    let mut media = BufferMedia(&mut data);
+    SLOTS.store(&mut media, 0, 1, &[1, 2, 3]).unwrap();
+    SLOTS.store(&mut media, 2, 4, &[9]).unwrap();
+
+    assert_eq!(SLOTS.load(&mut media, 0), Ok(SaveRecord { version: 1, payload: vec![1, 2, 3] }));
+    assert_eq!(SLOTS.load(&mut media, 1), Err(SaveError::Empty));
+    assert_eq!(SLOTS.load(&mut media, 2), Ok(SaveRecord { version: 4, payload: vec![9] }));
+  }
+
+  #[test]
+  fn newest_copy_wins() {
+    let mut data = [0xFF; 384];
+    let mut media = BufferMedia(&mut data);
+    for i in 0..5 {
+      SLOTS.store(&mut media, 1, 1, &[i]).unwrap();
+    }
+    assert_eq!(SLOTS.load(&mut media, 1).unwrap().payload, vec![4]);
+  }
+
+  #[test]
+  fn recovers_previous_copy_when_newest_is_corrupt() {
+    let mut data = [0xFF; 384];
+    {
+      let mut media = BufferMedia(&mut data);
+      SLOTS.store(&mut media, 0, 1, &[1]).unwrap();
+      SLOTS.store(&mut media, 0, 1, &[2]).unwrap();
+    }
+    // The second store went to the second copy of slot 0.
+    data[64 + HEADER_LEN] ^= 0xFF;
+    let mut media = BufferMedia(&mut data);
+    assert_eq!(SLOTS.load(&mut media, 0).unwrap().payload, vec![1]);
+
+    SLOTS.store(&mut media, 0, 1, &[3]).unwrap();
+    assert_eq!(SLOTS.load(&mut media, 0).unwrap().payload, vec![3]);
+  }
+
+  #[test]
+  fn reports_corruption_when_no_copy_is_valid() {
+    let mut data = [0xFF; 384];
+    {
+      let mut media = BufferMedia(&mut data);
+      SLOTS.store(&mut media, 0, 1, &[1, 2]).unwrap();
+    }
+    data[HEADER_LEN + 1] ^= 0x01;
+    let mut media = BufferMedia(&mut data);
+    assert_eq!(SLOTS.load(&mut media, 0), Err(SaveError::Corrupt));
+  }
+
+  #[test]
+  fn rejects_oversized_payloads_and_bad_slots() {
+    let mut data = [0xFF; 384];
+    let mut media = BufferMedia(&mut data);
+    assert_eq!(SLOTS.store(&mut media, 0, 1, &[0; 64]), Err(SaveError::TooLarge));
+    assert_eq!(SLOTS.store(&mut media, 3, 1, &[0]), Err(SaveError::OutOfBounds));
+  }
+
+  #[test]
+  fn erase_clears_slot() {
+    let mut data = [0xFF; 384];
+    let mut media = BufferMedia(&mut data);
+    SLOTS.store(&mut media, 0, 1, &[1]).unwrap();
+    SLOTS.erase(&mut media, 0).unwrap();
+    assert_eq!(SLOTS.load(&mut media, 0), Err(SaveError::Empty));
+  }
+
+  #[test]
+  fn offset_slots_leave_earlier_slots_alone() {
+    const LATER: SaveSlots = SaveSlots::new(1, 128).starting_at(SLOTS.end());
+    let mut data = [0xFF; 512];
+    let mut media = BufferMedia(&mut data);
+    SLOTS.store(&mut media, 2, 1, &[1]).unwrap();
+    LATER.store(&mut media, 0, 1, &[2]).unwrap();
+
+    assert_eq!(SLOTS.load(&mut media, 2).unwrap().payload, vec![1]);
+    assert_eq!(LATER.load(&mut media, 0).unwrap().payload, vec![2]);
+    assert_eq!(LATER.store(&mut BufferMedia(&mut [0xFF; 384]), 0, 1, &[2]), Err(SaveError::OutOfBounds));
+  }
+
+  #[test]
+  fn byte_reader_reads_what_writer_wrote() {
+    let mut writer = ByteWriter::new();
+    writer.u8(7).u16(0xBEEF).u32(123_456).i32(-42);
+    let bytes = writer.finish();
+    let mut reader = ByteReader::new(&bytes);
+    assert_eq!(reader.u8(), Ok(7));
+    assert_eq!(reader.u16(), Ok(0xBEEF));
+    assert_eq!(reader.u32(), Ok(123_456));
+    assert_eq!(reader.i32(), Ok(-42));
+    assert_eq!(reader.u8(), Err(SaveError::Corrupt));
+  }
+}
@@ -1,23 +1,21 @@
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 use core::mem::transmute;
 use agb::{display::{
-  tiled::{MapLoan, VRamManager, RegularMap, TileSet, TileSetting},
+  tiled::{MapLoan, VRamManager, RegularMap, TileSet, TileSetting, Tiled0, TiledMap, RegularBackgroundSize, TileFormat, BackgroundID},
   tile_data::TileData,
   palette16::Palette16,
+  Priority,
+  WIDTH, HEIGHT,
 }, fixnum::{Vector2D, Rect}};
 use crate::{
-  math::{PosNum, ZERO, MIN_INC},
-  camera::Camera,
-  collision::{CollideTileType, CollideTilemap},
+  math::{PosNum, MIN_INC},
+  camera::{Camera, Parallax},
+  palette::PaletteFader,
+  collision::CollideTilemap,
 };
 
-#[derive(Clone, Copy)]
-pub enum FlipTile<I> {
-  N(I),
-  X(I),
-  Y(I),
-  XY(I),
-}
+pub use crate::rle::{RleLayer, RleRow};
+pub use crate::overlay::{FlipTile, TilemapLayer, TileEdit, TileOverlay};
 
 #[derive(Clone, Copy)]
 pub struct Metatile {
@@ -31,64 +29,66 @@ pub struct TileSetData {
   pub metatiles: &'static [Metatile],
   pub palettes: &'static [Palette16],
   pub tile_data: &'static TileData,
+  pub animations: &'static [TileAnimation],
+}
+
+#[derive(Clone, Copy)]
+pub struct AnimatedTileFrame {
+  pub tile: u16,
+  pub duration: u16,
+}
+
+pub struct TileAnimation {
+  pub tile: u16,
+  pub frames: &'static [AnimatedTileFrame],
+}
+
+/// Tiles across and down the 32x32 backgrounds layers are drawn on.
+const MAP_TILES: i32 = 32;
+/// Tiles on screen at once, counting the partly scrolled-in ones at the edges.
+const SCREEN_TILES: Vector2D<i32> = Vector2D { x: WIDTH / 8 + 1, y: HEIGHT / 8 + 1 };
+
+type TileFn<'a> = Box<dyn Fn(Vector2D<i32>) -> (&'a TileSet<'a>, TileSetting) + 'a>;
+
+pub struct TileAnimator {
+  tileset_data: &'static TileSetData,
+  states: Vec<(usize, u16)>,
 }
 
 #[derive(Clone, Copy)]
 pub struct Tilemap {
-  data: &'static [FlipTile<u8>],
-  background_data: Option<&'static [FlipTile<u8>]>,
-  foreground_data: Option<&'static [FlipTile<u8>]>,
-  collision_data: &'static [CollideTileType],
+  data: RleLayer,
+  background_data: Option<RleLayer>,
+  foreground_data: Option<RleLayer>,
+  collision_data: RleLayer,
   width: usize,
   height: usize,
+  /// The world metatile the map's top left metatile sits at.
+  origin: Vector2D<i32>,
   tileset: &'static TileSet<'static>,
   tileset_data: &'static TileSetData,
+  palettes: &'static [Palette16],
 }
 
+/// Keeps a background filled with the tiles around its scroll position. Tile (x, y) always lives at entry
+/// (x mod 32, y mod 32), so scrolling only writes the rows and columns coming on screen, and single tiles can be
+/// rewritten in place.
+struct ScrolledMap<'a> {
+  map: MapLoan<'a, RegularMap>,
+  tile_fn: TileFn<'a>,
+  /// The top left tile on screen.
+  tile_pos: Vector2D<i32>,
+}
 
-impl<I> FlipTile<I> {
-  pub fn idx(self) -> I {
-    match self {
-      Self::N(idx) => idx,
-      Self::X(idx) => idx,
-      Self::Y(idx) => idx,
-      Self::XY(idx) => idx,
-    }
-  }
-
-  pub fn x_flipped(self) -> bool {
-    match self {
-      Self::X(_) => true,
-      Self::XY(_) => true,
-      _ => false,
-    }
-  }
-
-  pub fn y_flipped(self) -> bool {
-    match self {
-      Self::Y(_) => true,
-      Self::XY(_) => true,
-      _ => false,
-    }
-  }
-
-  pub fn flip_x(self) -> Self {
-    match self {
-      Self::N(idx) => Self::X(idx),
-      Self::X(idx) => Self::N(idx),
-      Self::Y(idx) => Self::XY(idx),
-      Self::XY(idx) => Self::Y(idx),
-    }
-  }
+pub struct ParallaxLayer<'a> {
+  map: ScrolledMap<'a>,
+  parallax: Parallax,
+}
 
-  pub fn flip_y(self) -> Self {
-    match self {
-      Self::N(idx) => Self::Y(idx),
-      Self::Y(idx) => Self::N(idx),
-      Self::X(idx) => Self::XY(idx),
-      Self::XY(idx) => Self::X(idx),
-    }
-  }
+pub struct TilemapLayers<'a> {
+  background: Option<ParallaxLayer<'a>>,
+  primary: ParallaxLayer<'a>,
+  foreground: Option<ParallaxLayer<'a>>,
 }
 
 impl Metatile {
@@ -117,10 +117,10 @@ impl Metatile {
 
 impl Tilemap {
   pub const fn new(
-      data: &'static [FlipTile<u8>],
-      bg: Option<&'static [FlipTile<u8>]>,
-      fg: Option<&'static [FlipTile<u8>]>,
-      col: &'static [CollideTileType],
+      data: RleLayer,
+      bg: Option<RleLayer>,
+      fg: Option<RleLayer>,
+      col: RleLayer,
       width: usize,
       tileset_data: &'static TileSetData) -> Self {
     Tilemap {
@@ -129,29 +129,62 @@ impl Tilemap {
       foreground_data: fg,
       collision_data: col,
       width,
-      height: data.len() / width,
+      height: data.height(),
+      origin: Vector2D::new(0, 0),
       tileset: &tileset_data.tile_data.tiles,
       tileset_data,
+      palettes: tileset_data.palettes,
     }
   }
 
-  pub fn primary_tile_fn<'a>(&'a self) -> Box<dyn Fn(Vector2D<i32>) -> (&'a TileSet<'a>, TileSetting) + 'a> {
+  /// Draws with another palette set laid out like the tileset's own, e.g. a night version of a room.
+  pub const fn with_palettes(self, palettes: &'static [Palette16]) -> Self {
+    Tilemap {
+      palettes,
+      ..self
+    }
+  }
+
+  /// Places the map in the world, so connected rooms can share continuous coordinates. Camera positions, tile edits
+  /// and collision are all in world coordinates.
+  pub const fn with_origin(self, origin: Vector2D<i32>) -> Self {
+    Tilemap {
+      origin,
+      ..self
+    }
+  }
+
+  /// Draws the same layout with another tileset's art, e.g. a room reused in a different biome.
+  pub const fn with_tileset(self, tileset_data: &'static TileSetData) -> Self {
+    Tilemap {
+      tileset: &tileset_data.tile_data.tiles,
+      tileset_data,
+      palettes: tileset_data.palettes,
+      ..self
+    }
+  }
+
+  pub fn tileset_data(&self) -> &'static TileSetData {
+    self.tileset_data
+  }
+
+  pub fn primary_tile_fn<'a>(&'a self, overlay: &'a TileOverlay) -> TileFn<'a> {
     let self_clone = self.clone();
     Box::new(move |pos| {
       (
         self_clone.tileset,
-        self_clone.get_tile(self_clone.data, pos),
+        self_clone.get_tile(self_clone.data, TilemapLayer::Primary, overlay, pos),
       )
     })
   }
 
-  pub fn background_tile_fn<'a>(&'a self) -> Box<dyn Fn(Vector2D<i32>) -> (&'a TileSet<'a>, TileSetting) + 'a> {
+  pub fn background_tile_fn<'a>(&'a self, overlay: &'a TileOverlay) -> TileFn<'a> {
     let self_clone = self.clone();
     if let Some(data) = self.background_data {
       Box::new(move |pos| {
         (
           self_clone.tileset,
-          self_clone.get_tile(data, pos),
+          self_clone.get_tile(data, TilemapLayer::Background, overlay, pos),
         )
       })
     } else {
@@ -159,13 +192,13 @@ impl Tilemap {
     }
   }
 
-  pub fn foreground_tile_fn<'a>(&'a self) -> Box<dyn Fn(Vector2D<i32>) -> (&'a TileSet<'a>, TileSetting) + 'a> {
+  pub fn foreground_tile_fn<'a>(&'a self, overlay: &'a TileOverlay) -> TileFn<'a> {
     let self_clone = self.clone();
     if let Some(data) = self.foreground_data {
       Box::new(move |pos| {
         (
           self_clone.tileset,
-          self_clone.get_tile(data, pos),
+          self_clone.get_tile(data, TilemapLayer::Foreground, overlay, pos),
         )
       })
     } else {
@@ -173,14 +206,21 @@ impl Tilemap {
     }
   }
 
-  fn get_tile(&self, data: &'static [FlipTile<u8>], pos: Vector2D<i32>) -> TileSetting {
-    let metatile_pos = pos / 2;
-    let lower = pos.y % 2 == 1;
-    let right = pos.x % 2 == 1;
-    if metatile_pos.x < 0 || metatile_pos.x >= self.width as i32 || metatile_pos.y < 0 || metatile_pos.y >= self.height as i32 {
+  fn get_tile(&self, data: RleLayer, layer: TilemapLayer, overlay: &TileOverlay, pos: Vector2D<i32>) -> TileSetting {
+    // Rooms smaller than the screen are centred, so tiles left of and above the map are asked for too
+    let metatile_pos = Vector2D::new(pos.x.div_euclid(2), pos.y.div_euclid(2));
+    let lower = pos.y.rem_euclid(2) == 1;
+    let right = pos.x.rem_euclid(2) == 1;
+    let local_pos = metatile_pos - self.origin;
+    if local_pos.x < 0 || local_pos.x >= self.width as i32 || local_pos.y < 0 || local_pos.y >= self.height as i32 {
       return TileSetting::BLANK;
     }
-    let metatile_flip_idx = data[metatile_pos.x as usize + self.width * metatile_pos.y as usize];
+    let metatile_flip_idx = overlay.metatile(layer, metatile_pos).unwrap_or_else(|| {
+      // The index is stored little endian in however many bytes the level was exported with
+      let (idx_bytes, flip) = data.get(local_pos.x as usize, local_pos.y as usize).split_at(data.value_size() - 1);
+      let idx = idx_bytes.iter().rev().fold(0, |idx, byte| idx << 8 | *byte as u16);
+      FlipTile::from_flip_bits(idx, flip[0]).unwrap_or(FlipTile::N(0))
+    });
     if metatile_flip_idx.idx() > 0 {
       let metatile = {
         let metatile_idx = (metatile_flip_idx.idx() - 1) as usize;
@@ -208,12 +248,38 @@ impl Tilemap {
     }
   }
 
+  pub fn create_layers<'a>(&'a self, tiled0: &'a Tiled0<'_>, overlay: &'a TileOverlay, vram: &mut VRamManager, camera: &Camera) -> TilemapLayers<'a> {
+    let new_layer = |priority, tile_fn| {
+      let map = tiled0.background(priority, RegularBackgroundSize::Background32x32, TileFormat::FourBpp);
+      ParallaxLayer::new(map, tile_fn, Parallax::LOCKED)
+    };
+    let mut layers = TilemapLayers {
+      background: self.background_data.map(|_| new_layer(Priority::P3, self.background_tile_fn(overlay))),
+      primary: new_layer(Priority::P2, self.primary_tile_fn(overlay)),
+      foreground: self.foreground_data.map(|_| new_layer(Priority::P1, self.foreground_tile_fn(overlay))),
+    };
+    layers.init(vram, camera);
+    layers
+  }
+
   pub fn load_tileset_palette(&self, vram: &mut VRamManager) {
-    vram.set_background_palettes(self.tileset_data.palettes);
+    vram.set_background_palettes(self.palettes);
+  }
+
+  pub fn palettes(&self) -> &'static [Palette16] {
+    self.palettes
+  }
+
+  pub fn palette_fader(&self) -> PaletteFader {
+    PaletteFader::new(self.palettes)
+  }
+
+  pub fn tile_animator(&self) -> TileAnimator {
+    TileAnimator::new(self.tileset_data)
   }
 
   pub fn set_camera_limits(&self, camera: &mut Camera) {
-    camera.set_limits((PosNum::new(self.width as i32 * 16), PosNum::new(self.height as i32 * 16)).into())
+    camera.set_limits(Rect::new((self.origin * 16).change_base(), (PosNum::new(self.width as i32 * 16), PosNum::new(self.height as i32 * 16)).into()))
   }
 
   fn flipped_tile_settings(tile_settings: &[TileSetting], tile_idx: FlipTile<usize>) -> TileSetting {
@@ -232,12 +298,228 @@ impl Tilemap {
   }
 }
 
+impl TileAnimator {
+  pub fn new(tileset_data: &'static TileSetData) -> Self {
+    TileAnimator {
+      tileset_data,
+      states: tileset_data.animations.iter().map(|anim| (0, anim.frames.first().map_or(0, |frame| frame.duration))).collect(),
+    }
+  }
+
+  /// Advances every animation by one frame, copying the current frame's graphics into the animated tile's VRAM slot.
+  /// Call after vblank so the swap doesn't tear.
+  ///
+  /// The copy happens every frame rather than only on a swap: a tile that scrolled off screen is freed, and when it
+  /// comes back it's reallocated with the tileset's own graphics, so it would show frame 0 until the next swap.
+  /// Tiles that aren't allocated are skipped by `replace_tile`.
+  pub fn update(&mut self, vram: &mut VRamManager) {
+    let tileset = &self.tileset_data.tile_data.tiles;
+    for (anim, (frame_idx, timer)) in self.tileset_data.animations.iter().zip(self.states.iter_mut()) {
+      if anim.frames.len() < 2 {
+        continue;
+      }
+      *timer = timer.saturating_sub(1);
+      if *timer == 0 {
+        *frame_idx = (*frame_idx + 1) % anim.frames.len();
+        *timer = anim.frames[*frame_idx].duration;
+      }
+      let frame = anim.frames[*frame_idx];
+      if frame.tile != anim.tile {
+        vram.replace_tile(tileset, anim.tile, tileset, frame.tile);
+      }
+    }
+  }
+}
+
+impl<'a> ScrolledMap<'a> {
+  fn new(map: MapLoan<'a, RegularMap>, tile_fn: TileFn<'a>) -> Self {
+    ScrolledMap { map, tile_fn, tile_pos: Vector2D::new(0, 0) }
+  }
+
+  /// Draws every tile on screen at `pos`.
+  fn init(&mut self, vram: &mut VRamManager, pos: Vector2D<i32>) {
+    self.scroll_to(pos);
+    self.draw_rect(vram, self.tile_pos, SCREEN_TILES);
+  }
+
+  /// Draws just the tiles scrolled onto the screen since the last position.
+  fn set_pos(&mut self, vram: &mut VRamManager, pos: Vector2D<i32>) {
+    let old_tile_pos = self.tile_pos;
+    self.scroll_to(pos);
+    let (new_tile_pos, moved) = (self.tile_pos, self.tile_pos - old_tile_pos);
+    if moved.x.abs() >= SCREEN_TILES.x || moved.y.abs() >= SCREEN_TILES.y {
+      self.draw_rect(vram, new_tile_pos, SCREEN_TILES);
+      return;
+    }
+    if moved.x > 0 {
+      self.draw_rect(vram, (old_tile_pos.x + SCREEN_TILES.x, new_tile_pos.y).into(), (moved.x, SCREEN_TILES.y).into());
+    } else if moved.x < 0 {
+      self.draw_rect(vram, new_tile_pos, (-moved.x, SCREEN_TILES.y).into());
+    }
+    if moved.y > 0 {
+      self.draw_rect(vram, (new_tile_pos.x, old_tile_pos.y + SCREEN_TILES.y).into(), (SCREEN_TILES.x, moved.y).into());
+    } else if moved.y < 0 {
+      self.draw_rect(vram, new_tile_pos, (SCREEN_TILES.x, -moved.y).into());
+    }
+  }
+
+  /// Rewrites one tile if it's on screen. Off-screen tiles are drawn when they scroll on.
+  fn redraw_tile(&mut self, vram: &mut VRamManager, tile: Vector2D<i32>) {
+    let offset = tile - self.tile_pos;
+    if (0..SCREEN_TILES.x).contains(&offset.x) && (0..SCREEN_TILES.y).contains(&offset.y) {
+      self.draw_tile(vram, tile);
+    }
+  }
+
+  fn scroll_to(&mut self, pos: Vector2D<i32>) {
+    self.tile_pos = Vector2D::new(pos.x.div_euclid(8), pos.y.div_euclid(8));
+    let wrap = MAP_TILES * 8;
+    self.map.set_scroll_pos((pos.x.rem_euclid(wrap) as i16, pos.y.rem_euclid(wrap) as i16));
+  }
+
+  fn draw_rect(&mut self, vram: &mut VRamManager, start: Vector2D<i32>, size: Vector2D<i32>) {
+    for y in start.y..start.y + size.y {
+      for x in start.x..start.x + size.x {
+        self.draw_tile(vram, Vector2D::new(x, y));
+      }
+    }
+  }
+
+  fn draw_tile(&mut self, vram: &mut VRamManager, tile: Vector2D<i32>) {
+    let (tileset, tile_setting) = (self.tile_fn)(tile);
+    let entry = (tile.x.rem_euclid(MAP_TILES) as u16, tile.y.rem_euclid(MAP_TILES) as u16);
+    self.map.set_tile(vram, entry, tileset, tile_setting);
+  }
+}
+
+impl<'a> ParallaxLayer<'a> {
+  pub fn new(map: MapLoan<'a, RegularMap>, tile_fn: TileFn<'a>, parallax: Parallax) -> Self {
+    ParallaxLayer {
+      map: ScrolledMap::new(map, tile_fn),
+      parallax,
+    }
+  }
+
+  pub fn parallax(&self) -> Parallax {
+    self.parallax
+  }
+
+  pub fn set_parallax(&mut self, parallax: Parallax) {
+    self.parallax = parallax;
+  }
+
+  pub fn background(&self) -> BackgroundID {
+    self.map.map.background()
+  }
+
+  pub fn set_priority(&mut self, priority: Priority) {
+    self.map.map.set_priority(priority);
+  }
+
+  pub fn init(&mut self, vram: &mut VRamManager, camera: &Camera) {
+    self.map.init(vram, self.parallax.scroll_pos(camera));
+    self.map.map.commit(vram);
+    self.map.map.set_visible(true);
+  }
+
+  pub fn set_pos(&mut self, vram: &mut VRamManager, camera: &Camera) {
+    self.parallax.tick();
+    self.map.set_pos(vram, self.parallax.scroll_pos(camera));
+  }
+
+  /// Rewrites the four tiles of a metatile, if they're on screen.
+  fn redraw_metatile(&mut self, vram: &mut VRamManager, pos: Vector2D<i32>) {
+    for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+      self.map.redraw_tile(vram, pos * 2 + (x, y).into());
+    }
+  }
+
+  pub fn commit(&mut self, vram: &mut VRamManager) {
+    self.map.map.commit(vram);
+  }
+
+  pub fn clear(&mut self, vram: &mut VRamManager) {
+    self.map.map.clear(vram);
+  }
+}
+
+impl<'a> TilemapLayers<'a> {
+  pub fn layer(&self, layer: TilemapLayer) -> Option<&ParallaxLayer<'a>> {
+    match layer {
+      TilemapLayer::Background => self.background.as_ref(),
+      TilemapLayer::Primary => Some(&self.primary),
+      TilemapLayer::Foreground => self.foreground.as_ref(),
+    }
+  }
+
+  pub fn layer_mut(&mut self, layer: TilemapLayer) -> Option<&mut ParallaxLayer<'a>> {
+    match layer {
+      TilemapLayer::Background => self.background.as_mut(),
+      TilemapLayer::Primary => Some(&mut self.primary),
+      TilemapLayer::Foreground => self.foreground.as_mut(),
+    }
+  }
+
+  pub fn background_id(&self, layer: TilemapLayer) -> Option<BackgroundID> {
+    self.layer(layer).map(|layer| layer.background())
+  }
+
+  pub fn set_priority(&mut self, layer: TilemapLayer, priority: Priority) {
+    if let Some(layer) = self.layer_mut(layer) {
+      layer.set_priority(priority);
+    }
+  }
+
+  pub fn set_parallax(&mut self, vram: &mut VRamManager, camera: &Camera, layer: TilemapLayer, parallax: Parallax) {
+    if let Some(layer) = self.layer_mut(layer) {
+      layer.set_parallax(parallax);
+      layer.init(vram, camera);
+    }
+  }
+
+  pub fn set_pos(&mut self, vram: &mut VRamManager, camera: &Camera) {
+    for layer in self.iter_mut() {
+      layer.set_pos(vram, camera);
+    }
+  }
+
+  /// Rewrites the on-screen tiles of each edited metatile. Off-screen edits are picked up as the map scrolls. Call
+  /// after vblank, before `commit`, so the change doesn't tear.
+  pub fn redraw(&mut self, vram: &mut VRamManager, overlay: &TileOverlay) {
+    for edit in overlay.take_dirty() {
+      if let Some(layer) = self.layer_mut(edit.layer) {
+        layer.redraw_metatile(vram, edit.pos);
+      }
+    }
+  }
+
+  pub fn commit(&mut self, vram: &mut VRamManager) {
+    for layer in self.iter_mut() {
+      layer.commit(vram);
+    }
+  }
+
+  pub fn clear(&mut self, vram: &mut VRamManager) {
+    for layer in self.iter_mut() {
+      layer.clear(vram);
+    }
+  }
+
+  fn init(&mut self, vram: &mut VRamManager, camera: &Camera) {
+    for layer in self.iter_mut() {
+      layer.init(vram, camera);
+    }
+  }
+
+  fn iter_mut(&mut self) -> impl Iterator<Item=&mut ParallaxLayer<'a>> {
+    self.background.iter_mut()
+      .chain(core::iter::once(&mut self.primary))
+      .chain(self.foreground.iter_mut())
+  }
+}
+
 impl Into<CollideTilemap> for Tilemap {
   fn into(self) -> CollideTilemap {
-    CollideTilemap {
-      data: self.collision_data,
-      width: self.width,
-      height: self.height,
-    }
+    CollideTilemap::new(self.collision_data, self.width, self.height).with_origin(self.origin)
   }
 }
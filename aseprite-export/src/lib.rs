@@ -0,0 +1,327 @@
+use agb_ext::math::frames_from_ms;
+use asefile::{AnimationDirection, AsepriteFile, Slice, Tag};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Result, Write};
+use std::path::Path;
+
+/// Each file's tags become an `anim_enum!` and a `get_next_anim` in `{name}_anims.rs` in `OUT_DIR`, to be `include!`d
+/// next to the file's `include_aseprite!`.
+pub const ANIMATIONS: &[AnimationConfig] = &[
+  AnimationConfig { name: "gramble", anim_enum: "AnimEnum", graphics: "GRAPHICS" },
+  AnimationConfig { name: "gramble_pipe", anim_enum: "AnimEnum", graphics: "GRAPHICS" },
+];
+
+/// Each metasprite is laid out in `{gfx_dir}/{name}.aseprite` and written to `{name}.rs` in `OUT_DIR`, to be
+/// `include!`d where its parts' graphics are in scope.
+pub const METASPRITES: &[MetaspriteConfig] = &[];
+
+/// Names of the animation events frames can mark, written as `AnimEvents` constants to `anim_events.rs` in `OUT_DIR`
+/// and included at `ANIM_EVENTS_PATH`. At most 16, one per bit.
+pub const ANIM_EVENTS: &[&str] = &["Footstep", "Dust", "Hitbox"];
+const ANIM_EVENTS_PATH: &str = "crate::anim_event";
+/// A hidden layer whose cels' user data lists the events of their frame.
+const EVENTS_LAYER: &str = "Events";
+
+pub struct AnimationConfig {
+  pub name: &'static str,
+  pub anim_enum: &'static str,
+  /// Path to the file's `Graphics`.
+  pub graphics: &'static str,
+}
+
+pub struct MetaspriteConfig {
+  pub name: &'static str,
+  /// Path to the `Graphics` holding a tag for every part.
+  pub graphics: &'static str,
+}
+
+/// Collects every problem found in a file so they can all be reported before failing the build.
+struct Diagnostics {
+  path: String,
+  errors: Vec<String>,
+}
+
+impl Diagnostics {
+  fn new(path: &str) -> Self {
+    Diagnostics { path: path.to_string(), errors: vec![] }
+  }
+
+  fn tag_error(&mut self, tag: &Tag, message: impl Display) {
+    self.errors.push(format!("tag \"{}\": {message}", tag.name()));
+  }
+
+  fn slice_error(&mut self, slice: &Slice, message: impl Display) {
+    self.errors.push(format!("slice \"{}\": {message}", slice.name));
+  }
+
+  fn finish(self) {
+    if !self.errors.is_empty() {
+      for error in &self.errors {
+        println!("cargo::warning={}: {error}", self.path);
+      }
+      panic!("{} has {} error(s):\n  {}", self.path, self.errors.len(), self.errors.join("\n  "));
+    }
+  }
+}
+
+/// Exports every animation and metasprite above from `gfx_dir` into `out_dir`.
+pub fn export_all(gfx_dir: &str, out_dir: &str) -> Result<()> {
+  export_anim_events(out_dir)?;
+  for animation in ANIMATIONS {
+    export_animation(animation, gfx_dir, out_dir)?;
+  }
+  for metasprite in METASPRITES {
+    export_metasprite(metasprite, gfx_dir, out_dir)?;
+  }
+  Ok(())
+}
+
+fn load_file(path: &str) -> AsepriteFile {
+  println!("cargo::rerun-if-changed={path}");
+  AsepriteFile::read_file(Path::new(path)).unwrap_or_else(|err| panic!("{path}: couldn't load file: {err}"))
+}
+
+/// Writes an `AnimEvents` constant for each name in `ANIM_EVENTS`.
+pub fn export_anim_events(out_dir: &str) -> Result<()> {
+  assert!(ANIM_EVENTS.len() <= 16, "AnimEvents holds at most 16 events, but ANIM_EVENTS has {}", ANIM_EVENTS.len());
+  let output_file = File::create(format!("{out_dir}/anim_events.rs"))?;
+  let mut writer = BufWriter::new(output_file);
+  for (bit, event) in ANIM_EVENTS.iter().enumerate() {
+    writeln!(&mut writer, "pub const {}: agb_ext::anim::AnimEvents = agb_ext::anim::AnimEvents::bit({bit});", const_name(event))?;
+  }
+  Ok(())
+}
+
+/// The `AnimEvents` expression for each frame of the file, from the user data of its cel in the events layer.
+fn frame_events(file: &AsepriteFile, diagnostics: &mut Diagnostics) -> Vec<String> {
+  let layer = file.layer_by_name(EVENTS_LAYER);
+  if layer.as_ref().is_some_and(|layer| layer.is_visible()) {
+    diagnostics.errors.push(format!("layer \"{EVENTS_LAYER}\" must be hidden so its cels aren't drawn"));
+  }
+  (0..file.num_frames()).map(|frame_idx| {
+    let cel = layer.as_ref().map(|layer| file.cel(frame_idx, layer.id()));
+    let text = cel.as_ref().and_then(|cel| cel.user_data()).and_then(|user_data| user_data.text.as_deref()).unwrap_or("");
+    let events: Vec<String> = text.split_whitespace().filter_map(|event| {
+      if !ANIM_EVENTS.contains(&event) {
+        diagnostics.errors.push(format!("frame {frame_idx}: unknown event \"{event}\", expected one of {ANIM_EVENTS:?}"));
+        return None;
+      }
+      Some(format!("{ANIM_EVENTS_PATH}::{}", const_name(event)))
+    }).collect();
+    match events.split_first() {
+      None => "agb_ext::anim::AnimEvents::NONE".to_string(),
+      Some((first, rest)) => rest.iter().fold(first.clone(), |events, event| format!("{events}.union({event})")),
+    }
+  }).collect()
+}
+
+/// How a tag plays, read from the file and the tag's user data. The user data can hold `next=Tag` to play the tag
+/// once, or as many times as its repeat count, then move on to that tag. Without it, the tag loops.
+struct TagAnim {
+  name: String,
+  /// The tag's first frame, which frame indices within the tag count from.
+  from: u32,
+  frames: Vec<u32>,
+  next_anim: usize,
+}
+
+impl TagAnim {
+  fn parse_all(file: &AsepriteFile, diagnostics: &mut Diagnostics) -> Vec<TagAnim> {
+    let tags: Vec<&Tag> = (0..file.num_tags()).map(|tag_id| file.tag(tag_id)).collect();
+    if tags.is_empty() {
+      diagnostics.errors.push("no tags to export".to_string());
+    }
+    if tags.len() > u8::MAX as usize + 1 {
+      diagnostics.errors.push(format!("{} tags, but an AnimId holds at most 256", tags.len()));
+    }
+    tags.iter().enumerate().map(|(tag_id, tag)| {
+      let mut next_anim = None;
+      let settings = tag.user_data().and_then(|user_data| user_data.text.as_deref()).unwrap_or("");
+      for setting in settings.split_whitespace() {
+        match setting.split_once('=') {
+          Some(("next", next)) => match tags.iter().position(|tag| tag.name() == next) {
+            Some(next_tag_id) => next_anim = Some(next_tag_id),
+            None => diagnostics.tag_error(tag, format!("next tag \"{next}\" doesn't exist")),
+          },
+          _ => diagnostics.tag_error(tag, format!("unknown setting \"{setting}\", expected next=Tag")),
+        }
+      }
+      TagAnim { name: tag.name().to_string(), from: tag.from_frame(), frames: tag_frames(tag, next_anim.is_some()), next_anim: next_anim.unwrap_or(tag_id) }
+    }).collect()
+  }
+}
+
+/// The file frames a tag plays through, repeated when it moves on to another tag rather than looping. A ping-pong
+/// tag doesn't repeat its end frames on the way back.
+fn tag_frames(tag: &Tag, chained: bool) -> Vec<u32> {
+  let (from, to) = (tag.from_frame(), tag.to_frame());
+  let cycle: Vec<u32> = match tag.animation_direction() {
+    AnimationDirection::Forward => (from..=to).collect(),
+    AnimationDirection::Reverse => (from..=to).rev().collect(),
+    AnimationDirection::PingPong => (from..=to).chain((from + 1..to).rev()).collect(),
+  };
+  let repeat = if chained { tag.repeat().map_or(1, |repeat| repeat.get()) } else { 1 };
+  cycle.repeat(repeat as usize)
+}
+
+/// Writes an `anim_enum!` with a variant named after each tag, and a `get_next_anim` playing each tag's frames for
+/// `AnimPlayer`.
+pub fn export_animation(config: &AnimationConfig, gfx_dir: &str, out_dir: &str) -> Result<()> {
+  let path = format!("{gfx_dir}/{}.aseprite", config.name);
+  let file = load_file(&path);
+  let mut diagnostics = Diagnostics::new(&path);
+  let anims = TagAnim::parse_all(&file, &mut diagnostics);
+  let events = frame_events(&file, &mut diagnostics);
+  for anim in &anims {
+    if !is_ident(&anim.name) {
+      diagnostics.errors.push(format!("tag \"{}\" isn't a valid Rust identifier", anim.name));
+    }
+  }
+  diagnostics.finish();
+
+  let output_file = File::create(format!("{out_dir}/{}_anims.rs", config.name))?;
+  let mut writer = BufWriter::new(output_file);
+  let anim_enum = config.anim_enum;
+  writeln!(&mut writer, "agb_ext::anim_enum!(pub {anim_enum} {{")?;
+  let variants: Vec<String> = anims.iter().enumerate().map(|(anim_id, anim)| format!("  {} => {anim_id}", anim.name)).collect();
+  writeln!(&mut writer, "{}", variants.join(",\n"))?;
+  writeln!(&mut writer, "}});")?;
+
+  for anim in &anims {
+    writeln!(&mut writer)?;
+    writeln!(&mut writer, "static {}_FRAMES: &[agb_ext::anim::Frame] = &[", const_name(&anim.name))?;
+    for frame_idx in &anim.frames {
+      writeln!(
+        &mut writer,
+        "  agb_ext::anim::Frame {{ tag: {}.tags().get({:?}), tag_idx: {}, duration: {}, events: {} }},",
+        config.graphics, anim.name, frame_idx - anim.from, frame_duration(file.frame(*frame_idx).duration()),
+        events[*frame_idx as usize],
+      )?;
+    }
+    writeln!(&mut writer, "];")?;
+  }
+
+  writeln!(&mut writer)?;
+  writeln!(&mut writer, "pub fn get_next_anim(anim: AnimId) -> agb_ext::anim::Anim {{")?;
+  writeln!(&mut writer, "  match anim.into() {{")?;
+  for anim in &anims {
+    writeln!(
+      &mut writer,
+      "    {anim_enum}::{} => agb_ext::anim::Anim {{ frames: {}_FRAMES, next_anim: Some({anim_enum}::{}.into()) }},",
+      anim.name, const_name(&anim.name), anims[anim.next_anim].name,
+    )?;
+  }
+  writeln!(&mut writer, "  }}")?;
+  writeln!(&mut writer, "}}")?;
+  Ok(())
+}
+
+/// One part of a metasprite, read from a slice. The slice is named after the part's tag, optionally followed by `#`
+/// and the frame within that tag, and its user data can hold `hflip` and `vflip`.
+struct Part {
+  tag: String,
+  tag_idx: u8,
+  hflip: bool,
+  vflip: bool,
+}
+
+impl Part {
+  fn parse(slice: &Slice, diagnostics: &mut Diagnostics) -> Option<Part> {
+    let (tag, tag_idx) = match slice.name.split_once('#') {
+      Some((tag, tag_idx)) => match tag_idx.parse() {
+        Ok(tag_idx) => (tag, tag_idx),
+        Err(_) => {
+          diagnostics.slice_error(slice, format!("\"{tag_idx}\" isn't a frame index"));
+          return None;
+        }
+      },
+      None => (slice.name.as_str(), 0),
+    };
+    let mut part = Part { tag: tag.to_string(), tag_idx, hflip: false, vflip: false };
+    let flags = slice.user_data.as_ref().and_then(|user_data| user_data.text.as_deref()).unwrap_or("");
+    for flag in flags.split_whitespace() {
+      match flag {
+        "hflip" => part.hflip = true,
+        "vflip" => part.vflip = true,
+        _ => diagnostics.slice_error(slice, format!("unknown flag \"{flag}\", expected hflip or vflip")),
+      }
+    }
+    Some(part)
+  }
+}
+
+/// Writes a `MetaAnim` for each tag in the file, played as for `export_animation`, with an `AnimId` constant named
+/// after the tag and a `get_next_anim` for `Metasprite`. A slice places its part from its most recent key, and a key
+/// with no size hides it.
+pub fn export_metasprite(config: &MetaspriteConfig, gfx_dir: &str, out_dir: &str) -> Result<()> {
+  let path = format!("{gfx_dir}/{}.aseprite", config.name);
+  let file = load_file(&path);
+  let mut diagnostics = Diagnostics::new(&path);
+  let anims = TagAnim::parse_all(&file, &mut diagnostics);
+  let events = frame_events(&file, &mut diagnostics);
+  let parts: Vec<Option<Part>> = file.slices().iter().map(|slice| Part::parse(slice, &mut diagnostics)).collect();
+  diagnostics.finish();
+
+  let output_file = File::create(format!("{out_dir}/{}.rs", config.name))?;
+  let mut writer = BufWriter::new(output_file);
+  writeln!(&mut writer, "use agb_ext::anim::{{AnimId, MetaAnim, MetaFrame, SpritePart}};")?;
+  writeln!(&mut writer, "use agb_fixnum::Vector2D;")?;
+  writeln!(&mut writer)?;
+
+  for (anim_id, anim) in anims.iter().enumerate() {
+    writeln!(&mut writer, "pub const {}: AnimId = AnimId({anim_id});", const_name(&anim.name))?;
+  }
+
+  for anim in &anims {
+    writeln!(&mut writer)?;
+    writeln!(&mut writer, "static {}_FRAMES: &[MetaFrame] = &[", const_name(&anim.name))?;
+    for &frame_idx in &anim.frames {
+      writeln!(&mut writer, "  MetaFrame {{ parts: &[")?;
+      for (slice, part) in file.slices().iter().zip(&parts) {
+        let Some(part) = part else { continue };
+        let Some(key) = slice.keys.iter().rev().find(|key| key.from_frame <= frame_idx) else { continue };
+        if key.size.0 == 0 || key.size.1 == 0 {
+          continue;
+        }
+        writeln!(
+          &mut writer,
+          "    SpritePart {{ tag: {}.tags().get({:?}), tag_idx: {}, offset: Vector2D {{ x: {}, y: {} }}, size: Vector2D {{ x: {}, y: {} }}, hflip: {}, vflip: {} }},",
+          config.graphics, part.tag, part.tag_idx, key.origin.0, key.origin.1, key.size.0, key.size.1, part.hflip, part.vflip,
+        )?;
+      }
+      writeln!(
+        &mut writer,
+        "  ], size: Vector2D {{ x: {}, y: {} }}, duration: {}, events: {} }},",
+        file.width(), file.height(), frame_duration(file.frame(frame_idx).duration()), events[frame_idx as usize],
+      )?;
+    }
+    writeln!(&mut writer, "];")?;
+  }
+
+  writeln!(&mut writer)?;
+  writeln!(&mut writer, "pub fn get_next_anim(anim: AnimId) -> MetaAnim {{")?;
+  writeln!(&mut writer, "  match anim.0 {{")?;
+  for (anim_id, anim) in anims.iter().enumerate() {
+    let (name, next_name) = (const_name(&anim.name), const_name(&anims[anim.next_anim].name));
+    writeln!(&mut writer, "    {anim_id} => MetaAnim {{ frames: {name}_FRAMES, next_anim: Some({next_name}) }},")?;
+  }
+  writeln!(&mut writer, "    _ => panic!(\"Unexpected animation ID {{}}\", anim.0),")?;
+  writeln!(&mut writer, "  }}")?;
+  writeln!(&mut writer, "}}")?;
+  Ok(())
+}
+
+/// Converts a duration in milliseconds to the nearest whole number of frames, at least one.
+fn frame_duration(ms: u32) -> u8 {
+  frames_from_ms(ms).clamp(1, u8::MAX as u32) as u8
+}
+
+fn const_name(tag_name: &str) -> String {
+  tag_name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect()
+}
+
+fn is_ident(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
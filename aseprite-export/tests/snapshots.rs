@@ -0,0 +1,49 @@
+use std::fs;
+use aseprite_export::{export_anim_events, export_animation, export_metasprite, ANIMATIONS, MetaspriteConfig};
+
+const GFX_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../gfx");
+const TEST_GFX_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/gfx");
+const SNAPSHOTS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots");
+
+/// Compares against `tests/snapshots/{name}.rs`. Run with `UPDATE_SNAPSHOTS=1` to accept changes.
+fn assert_snapshot(name: &str, generated: &str) {
+  let path = format!("{SNAPSHOTS_DIR}/{name}.rs");
+  if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+    fs::write(&path, generated).unwrap();
+    return;
+  }
+  let expected = fs::read_to_string(&path)
+    .unwrap_or_else(|_| panic!("Missing snapshot {path}, run with UPDATE_SNAPSHOTS=1 to create it"));
+  assert!(generated == expected, "Generated {name}.rs doesn't match {path}:\n{generated}");
+}
+
+fn out_dir(name: &str) -> String {
+  let out_dir = format!("{}/{name}", env!("CARGO_TARGET_TMPDIR"));
+  fs::create_dir_all(&out_dir).unwrap();
+  out_dir
+}
+
+#[test]
+fn animations_match_snapshots() {
+  let out_dir = out_dir("animations");
+  for animation in ANIMATIONS {
+    export_animation(animation, GFX_DIR, &out_dir).unwrap();
+    let name = format!("{}_anims", animation.name);
+    assert_snapshot(&name, &fs::read_to_string(format!("{out_dir}/{name}.rs")).unwrap());
+  }
+}
+
+#[test]
+fn anim_events_are_bits_in_order() {
+  let out_dir = out_dir("anim_events");
+  export_anim_events(&out_dir).unwrap();
+  assert_snapshot("anim_events", &fs::read_to_string(format!("{out_dir}/anim_events.rs")).unwrap());
+}
+
+#[test]
+fn metasprite_parts_come_from_slices() {
+  let out_dir = out_dir("metasprites");
+  let config = MetaspriteConfig { name: "boss", graphics: "crate::boss::PARTS" };
+  export_metasprite(&config, TEST_GFX_DIR, &out_dir).unwrap();
+  assert_snapshot("boss", &fs::read_to_string(format!("{out_dir}/boss.rs")).unwrap());
+}
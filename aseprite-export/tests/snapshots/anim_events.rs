@@ -0,0 +1,3 @@
+pub const FOOTSTEP: agb_ext::anim::AnimEvents = agb_ext::anim::AnimEvents::bit(0);
+pub const DUST: agb_ext::anim::AnimEvents = agb_ext::anim::AnimEvents::bit(1);
+pub const HITBOX: agb_ext::anim::AnimEvents = agb_ext::anim::AnimEvents::bit(2);
@@ -0,0 +1,33 @@
+use agb_ext::anim::{AnimId, MetaAnim, MetaFrame, SpritePart};
+use agb_fixnum::Vector2D;
+
+pub const IDLE: AnimId = AnimId(0);
+pub const ATTACK: AnimId = AnimId(1);
+
+static IDLE_FRAMES: &[MetaFrame] = &[
+  MetaFrame { parts: &[
+    SpritePart { tag: crate::boss::PARTS.tags().get("Body"), tag_idx: 0, offset: Vector2D { x: 8, y: 0 }, size: Vector2D { x: 32, y: 32 }, hflip: false, vflip: false },
+    SpritePart { tag: crate::boss::PARTS.tags().get("Wing"), tag_idx: 0, offset: Vector2D { x: 0, y: 4 }, size: Vector2D { x: 16, y: 16 }, hflip: false, vflip: false },
+    SpritePart { tag: crate::boss::PARTS.tags().get("Wing"), tag_idx: 0, offset: Vector2D { x: 32, y: 4 }, size: Vector2D { x: 16, y: 16 }, hflip: true, vflip: false },
+  ], size: Vector2D { x: 48, y: 32 }, duration: 6, events: crate::anim_event::FOOTSTEP },
+  MetaFrame { parts: &[
+    SpritePart { tag: crate::boss::PARTS.tags().get("Body"), tag_idx: 0, offset: Vector2D { x: 8, y: 0 }, size: Vector2D { x: 32, y: 32 }, hflip: false, vflip: false },
+    SpritePart { tag: crate::boss::PARTS.tags().get("Wing"), tag_idx: 0, offset: Vector2D { x: 0, y: 2 }, size: Vector2D { x: 16, y: 16 }, hflip: false, vflip: false },
+    SpritePart { tag: crate::boss::PARTS.tags().get("Wing"), tag_idx: 0, offset: Vector2D { x: 32, y: 2 }, size: Vector2D { x: 16, y: 16 }, hflip: true, vflip: false },
+  ], size: Vector2D { x: 48, y: 32 }, duration: 6, events: agb_ext::anim::AnimEvents::NONE },
+];
+
+static ATTACK_FRAMES: &[MetaFrame] = &[
+  MetaFrame { parts: &[
+    SpritePart { tag: crate::boss::PARTS.tags().get("Body"), tag_idx: 0, offset: Vector2D { x: 8, y: 0 }, size: Vector2D { x: 32, y: 32 }, hflip: false, vflip: false },
+    SpritePart { tag: crate::boss::PARTS.tags().get("Claw"), tag_idx: 1, offset: Vector2D { x: 36, y: 20 }, size: Vector2D { x: 8, y: 8 }, hflip: true, vflip: true },
+  ], size: Vector2D { x: 48, y: 32 }, duration: 12, events: crate::anim_event::HITBOX.union(crate::anim_event::DUST) },
+];
+
+pub fn get_next_anim(anim: AnimId) -> MetaAnim {
+  match anim.0 {
+    0 => MetaAnim { frames: IDLE_FRAMES, next_anim: Some(IDLE) },
+    1 => MetaAnim { frames: ATTACK_FRAMES, next_anim: Some(IDLE) },
+    _ => panic!("Unexpected animation ID {}", anim.0),
+  }
+}
@@ -0,0 +1,30 @@
+agb_ext::anim_enum!(pub AnimEnum {
+  Idle => 0,
+  RunLeadup => 1,
+  Run => 2
+});
+
+static IDLE_FRAMES: &[agb_ext::anim::Frame] = &[
+  agb_ext::anim::Frame { tag: GRAPHICS.tags().get("Idle"), tag_idx: 0, duration: 30, events: agb_ext::anim::AnimEvents::NONE },
+  agb_ext::anim::Frame { tag: GRAPHICS.tags().get("Idle"), tag_idx: 1, duration: 5, events: agb_ext::anim::AnimEvents::NONE },
+  agb_ext::anim::Frame { tag: GRAPHICS.tags().get("Idle"), tag_idx: 2, duration: 5, events: agb_ext::anim::AnimEvents::NONE },
+  agb_ext::anim::Frame { tag: GRAPHICS.tags().get("Idle"), tag_idx: 3, duration: 30, events: agb_ext::anim::AnimEvents::NONE },
+  agb_ext::anim::Frame { tag: GRAPHICS.tags().get("Idle"), tag_idx: 2, duration: 5, events: agb_ext::anim::AnimEvents::NONE },
+  agb_ext::anim::Frame { tag: GRAPHICS.tags().get("Idle"), tag_idx: 1, duration: 5, events: agb_ext::anim::AnimEvents::NONE },
+];
+
+static RUNLEADUP_FRAMES: &[agb_ext::anim::Frame] = &[
+  agb_ext::anim::Frame { tag: GRAPHICS.tags().get("RunLeadup"), tag_idx: 0, duration: 60, events: agb_ext::anim::AnimEvents::NONE },
+];
+
+static RUN_FRAMES: &[agb_ext::anim::Frame] = &[
+  agb_ext::anim::Frame { tag: GRAPHICS.tags().get("Run"), tag_idx: 0, duration: 60, events: agb_ext::anim::AnimEvents::NONE },
+];
+
+pub fn get_next_anim(anim: AnimId) -> agb_ext::anim::Anim {
+  match anim.into() {
+    AnimEnum::Idle => agb_ext::anim::Anim { frames: IDLE_FRAMES, next_anim: Some(AnimEnum::Idle.into()) },
+    AnimEnum::RunLeadup => agb_ext::anim::Anim { frames: RUNLEADUP_FRAMES, next_anim: Some(AnimEnum::Run.into()) },
+    AnimEnum::Run => agb_ext::anim::Anim { frames: RUN_FRAMES, next_anim: Some(AnimEnum::Run.into()) },
+  }
+}
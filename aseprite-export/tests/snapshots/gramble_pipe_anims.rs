@@ -0,0 +1,13 @@
+agb_ext::anim_enum!(pub AnimEnum {
+  Idle => 0
+});
+
+static IDLE_FRAMES: &[agb_ext::anim::Frame] = &[
+  agb_ext::anim::Frame { tag: GRAPHICS.tags().get("Idle"), tag_idx: 0, duration: 6, events: agb_ext::anim::AnimEvents::NONE },
+];
+
+pub fn get_next_anim(anim: AnimId) -> agb_ext::anim::Anim {
+  match anim.into() {
+    AnimEnum::Idle => agb_ext::anim::Anim { frames: IDLE_FRAMES, next_anim: Some(AnimEnum::Idle.into()) },
+  }
+}
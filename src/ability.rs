@@ -0,0 +1,36 @@
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Ability {
+  DoubleJump = 0,
+  PipeEntry = 1,
+  Glide = 2,
+  WallJump = 3,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct Abilities(u32);
+
+pub struct AbilityPickup(pub Ability);
+
+pub struct AirJump(pub bool);
+
+impl Abilities {
+  pub const fn none() -> Self {
+    Self(0)
+  }
+
+  pub const fn from_bits(bits: u32) -> Self {
+    Self(bits)
+  }
+
+  pub fn bits(self) -> u32 {
+    self.0
+  }
+
+  pub fn has(self, ability: Ability) -> bool {
+    self.0 & (1 << ability as u32) != 0
+  }
+
+  pub fn grant(&mut self, ability: Ability) {
+    self.0 |= 1 << ability as u32;
+  }
+}
@@ -0,0 +1,45 @@
+use agb_ext::{
+  hw::Button,
+  input::{ActionState, Bindings},
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+  Left = 0,
+  Right = 1,
+  Up = 2,
+  Down = 3,
+  Jump = 4,
+  Swap = 5,
+  Interact = 6,
+  Dash = 7,
+  Pause = 8,
+}
+
+pub type Actions = ActionState<Action>;
+pub type ActionBindings = Bindings<Action>;
+
+impl agb_ext::input::Action for Action {
+  const ALL: &'static [Self] = &[
+    Action::Left, Action::Right, Action::Up, Action::Down,
+    Action::Jump, Action::Swap, Action::Interact, Action::Dash, Action::Pause,
+  ];
+
+  fn index(self) -> usize {
+    self as usize
+  }
+
+  fn default_chord(self) -> Button {
+    match self {
+      Action::Left => Button::LEFT,
+      Action::Right => Button::RIGHT,
+      Action::Up => Button::UP,
+      Action::Down => Button::DOWN,
+      Action::Jump => Button::B,
+      Action::Swap => Button::SELECT,
+      Action::Interact => Button::A,
+      Action::Dash => Button::R,
+      Action::Pause => Button::START,
+    }
+  }
+}
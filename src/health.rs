@@ -0,0 +1,94 @@
+use agb_fixnum::Vector2D;
+use agb_ext::{
+  math::PosNum,
+  collision::{Pos, CollideTilemap},
+  palette::{PaletteFader, FadeColour, MAX_FADE},
+};
+
+const INVULNERABLE_FRAMES: u8 = 60;
+const DAMAGE_FLASH_FRAMES: u8 = 8;
+
+pub struct Health {
+  pub hp: i32,
+  pub max_hp: i32,
+  invulnerable: u8,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum DeathState {
+  Alive,
+  FadingOut,
+  FadingIn,
+}
+
+pub struct Respawn {
+  pub checkpoint: Vector2D<PosNum>,
+  pub state: DeathState,
+}
+
+impl Health {
+  pub fn new(max_hp: i32) -> Self {
+    Self {
+      hp: max_hp,
+      max_hp,
+      invulnerable: 0,
+    }
+  }
+
+  pub fn damage(&mut self, amount: i32) {
+    if self.invulnerable == 0 && amount > 0 {
+      self.hp -= amount;
+      self.invulnerable = INVULNERABLE_FRAMES;
+    }
+  }
+
+  pub fn kill(&mut self) {
+    self.hp = 0;
+  }
+
+  pub fn restore(&mut self) {
+    self.hp = self.max_hp;
+    self.invulnerable = 0;
+  }
+
+  pub fn is_dead(&self) -> bool {
+    self.hp <= 0
+  }
+
+  pub fn is_invulnerable(&self) -> bool {
+    self.invulnerable > 0
+  }
+}
+
+impl Respawn {
+  pub fn new(checkpoint: Vector2D<PosNum>) -> Self {
+    Self {
+      checkpoint,
+      state: DeathState::Alive,
+    }
+  }
+}
+
+pub mod system {
+  use super::*;
+
+  pub fn tick_invulnerability(health: &mut Health) {
+    if health.invulnerable > 0 {
+      health.invulnerable -= 1;
+    }
+  }
+
+  pub fn damage_flash(health: &Health, palette_fader: &mut PaletteFader) {
+    let flash_frames = health.invulnerable.saturating_sub(INVULNERABLE_FRAMES - DAMAGE_FLASH_FRAMES);
+    if flash_frames > 0 || palette_fader.get_fade().0 == FadeColour::White {
+      palette_fader.set_fade(FadeColour::White, flash_frames * MAX_FADE / DAMAGE_FLASH_FRAMES);
+    }
+  }
+
+  pub fn fall_out_of_map(pos: &Pos, health: &mut Health, tilemap: &CollideTilemap) {
+    let bounds = tilemap.bounds();
+    if pos.0.y > bounds.position.y + bounds.size.y {
+      health.kill();
+    }
+  }
+}
@@ -0,0 +1,48 @@
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod player;
+pub mod object;
+pub mod world;
+pub mod health;
+pub mod save;
+pub mod replay;
+pub mod ability;
+pub mod action;
+pub mod settings;
+#[cfg(not(feature = "agb"))]
+pub mod sim;
+
+/// The `AnimEvents` frames can mark in Aseprite, see `ANIM_EVENTS` in aseprite-export.
+pub mod anim_event {
+  include!(concat!(env!("OUT_DIR"), "/anim_events.rs"));
+}
+
+#[cfg(feature = "agb")]
+pub mod tileset {
+  include!(concat!(env!("OUT_DIR"), "/tileset.rs"));
+}
+
+/// Every room, indexed by the room id saves store.
+pub static ROOMS: &[world::Room] = &[
+  world::Room {
+    #[cfg(feature = "agb")]
+    tilemap: &grambles_room::TILEMAP,
+    load_objects: grambles_room::load_objects,
+  },
+  world::Room {
+    #[cfg(feature = "agb")]
+    tilemap: &slope_test::TILEMAP,
+    load_objects: slope_test::load_objects,
+  },
+];
+pub const GRAMBLES_ROOM: u16 = 0;
+
+pub mod grambles_room {
+  include!(concat!(env!("OUT_DIR"), "/grambles_room.rs"));
+}
+
+pub mod slope_test {
+  include!(concat!(env!("OUT_DIR"), "/slope_test.rs"));
+}
@@ -6,16 +6,12 @@
 
 extern crate alloc;
 
-mod player;
-mod object;
-mod world;
-
 use alloc::vec::Vec;
 use agb::{
   display::{
-    Priority,
-    tiled::{RegularBackgroundSize, TileFormat, TiledMap, InfiniteScrolledMap, PartialUpdateStatus, VRamManager},
-    blend::{Blend, Layer as BlendLayerPriority, BlendMode},
+    tiled::{TiledMap, PartialUpdateStatus, VRamManager},
+    blend::{Blend, Layer as BlendLayerPriority},
+    window::WinIn,
   },
   fixnum::{Vector2D, Rect, Num},
   input::{Button, ButtonController},
@@ -23,27 +19,27 @@ use agb::{
 use agb::fixnum::num;
 use agb::sound::mixer::{Frequency, SoundChannel};
 use agb_ext::{
-  tiles::Tilemap,
-  math::PosNum,
-  camera::Camera,
+  tiles::{Tilemap, TilemapLayer, TileOverlay},
+  math::{PosNum, const_num_i32},
+  camera::{Camera, Parallax},
   collision::{ControllableEntity, Entity, Pos, Vel, Acc},
-  ecs::{MutEntityAccessor, HasEntity}
+  ecs::{MutEntityAccessor, HasEntity},
+  input::held_buttons,
+  save::SaveError,
 };
 use agb_ext::blend::ManagedBlend;
-use crate::player::{gramble};
-use crate::world::{World};
-
-pub mod tileset {
-  include!(concat!(env!("OUT_DIR"), "/tileset.rs"));
-}
-
-pub mod grambles_room {
-  include!(concat!(env!("OUT_DIR"), "/grambles_room.rs"));
-}
+use gramble_and_glyde::{
+  player::gramble,
+  world::World,
+  save::SaveGame,
+  replay::Replay,
+  settings::Settings,
+  action::Actions,
+  ROOMS,
+  GRAMBLES_ROOM,
+};
 
-pub mod slope_test {
-  include!(concat!(env!("OUT_DIR"), "/slope_test.rs"));
-}
+const SAVE_SLOT: usize = 0;
 
 pub mod sounds {
   use agb::fixnum::Num;
@@ -60,27 +56,12 @@ pub mod sounds {
 #[agb::entry]
 fn main(mut gba: agb::Gba) -> ! {
   let (tiled0, mut vram) = gba.display.video.tiled0();
-  let primary = tiled0.background(
-    Priority::P2,
-    RegularBackgroundSize::Background32x32,
-    TileFormat::FourBpp,
-  );
-  let foreground = tiled0.background(
-    Priority::P1,
-    RegularBackgroundSize::Background32x32,
-    TileFormat::FourBpp,
-  );
-
-  let tilemap: &Tilemap = &grambles_room::TILEMAP;
-  tilemap.load_tileset_palette(&mut vram);
 
   let mut input = ButtonController::new();
   let object = gba.display.object.get_managed();
   let mut camera = Camera::new();
-  let mut collide_tilemap = tilemap.clone().into();
 
   let mut world = World::new();
-  tilemap.set_camera_limits(&mut camera);
 
   let vblank = agb::interrupt::VBlank::get();
 
@@ -88,44 +69,143 @@ fn main(mut gba: agb::Gba) -> ! {
   mixer.enable();
   sounds::TITLE.play(&mut mixer);
 
-  let mut blend = gba.display.blend.get();
-  blend.set_blend_mode(BlendMode::Normal);
-  blend.set_background_enable(BlendLayerPriority::Bottom, primary.background(), true);
-  blend.set_background_enable(BlendLayerPriority::Top, foreground.background(), true);
-  blend.set_object_enable(BlendLayerPriority::Bottom, true);
-  let mut blend = ManagedBlend::new(blend);
 
-  let mut gramble = gramble(&mut world, &object, (48, 96).into());
+  world.load_room(GRAMBLES_ROOM, ROOMS[GRAMBLES_ROOM as usize].load_objects);
+
+  let player_start = world.player_start().expect("Grambles room has a PlayerStart object");
+  let mut gramble = gramble(&mut world, &object, player_start);
+  world.set_checkpoint(player_start);
   //let mut glyde = Player::glyde(&object, (80, 80).into());
   //let mut gramble_pipe = GramblePipe::new(&object, (19 * 16, 32).into());
 
-  grambles_room::load_objects(&mut world);
+  gba.save.init_sram();
+  let mut save_data = gba.save.access().ok();
+  // Holding L and R at boot plays back the last stored replay instead of reading the keypad.
+  let playback_replay = if input.is_pressed(Button::L) && input.is_pressed(Button::R) {
+    save_data.as_mut().and_then(|media| Replay::load(media).ok())
+  } else {
+    None
+  };
+  let (start_save, settings) = match &playback_replay {
+    Some(replay) => (replay.start(), replay.settings()),
+    None => (
+      save_data.as_mut().and_then(|media| SaveGame::load(media, SAVE_SLOT).ok()),
+      save_data.as_mut().and_then(|media| Settings::load(media).ok()).unwrap_or_default(),
+    ),
+  };
+  let start_save = start_save.filter(|save| world.apply_save(save, ROOMS));
+  let mut replay = Replay::new(start_save.as_ref(), &settings);
+  let mut playback = playback_replay.map(Replay::into_playback);
+  let mut actions = Actions::new();
+  let mut last_save_error = None;
+
+  // The map comes from whichever room the save restored
+  let tilemap: &Tilemap = ROOMS[world.room() as usize].tilemap;
+  tilemap.load_tileset_palette(&mut vram);
+  tilemap.set_camera_limits(&mut camera);
+  let mut collide_tilemap = tilemap.clone().into();
 
-  let mut primary = InfiniteScrolledMap::new(primary, tilemap.primary_tile_fn());
-  primary.init(&mut vram, (0, 0).into(), &mut || {});
-  primary.commit(&mut vram);
-  primary.set_visible(true);
-  let mut foreground = InfiniteScrolledMap::new(foreground, tilemap.foreground_tile_fn());
-  foreground.init(&mut vram, (0, 0).into(), &mut || {});
-  foreground.commit(&mut vram);
-  foreground.set_visible(true);
+  let overlay = TileOverlay::new();
+  world.sync_tiles(&overlay, &mut collide_tilemap);
+  let mut layers = tilemap.create_layers(&tiled0, &overlay, &mut vram, &camera);
+  let mut tile_animator = tilemap.tile_animator();
+  let mut palette_fader = tilemap.palette_fader();
+  layers.set_parallax(&mut vram, &camera, TilemapLayer::Background, Parallax::new(const_num_i32(0, 5), const_num_i32(0, 5)));
   object.commit();
 
+  let mut blend = ManagedBlend::new(gba.display.blend.get());
+  for layer in [TilemapLayer::Background, TilemapLayer::Primary] {
+    if let Some(background) = layers.background_id(layer) {
+      blend.set_background_enable(BlendLayerPriority::Bottom, background);
+    }
+  }
+  if let Some(foreground) = layers.background_id(TilemapLayer::Foreground) {
+    blend.set_background_enable(BlendLayerPriority::Top, foreground);
+  }
+  blend.set_object_enable(BlendLayerPriority::Bottom);
+
+  // Rooms smaller than the screen are centred, with everything outside them hidden behind the backdrop
+  let mut windows = gba.display.window.get();
+  {
+    let room_window = windows.win_in(WinIn::Win0);
+    for layer in [TilemapLayer::Background, TilemapLayer::Primary, TilemapLayer::Foreground] {
+      if let Some(background) = layers.background_id(layer) {
+        room_window.set_background_enable(background, true);
+      }
+    }
+    room_window.set_object_enable(true).set_blend_enable(true);
+  }
+
   loop {
-    primary.set_pos(&mut vram, camera.position().trunc());
-    foreground.set_pos(&mut vram, camera.position().trunc());
-    world.frame(&input, &object, &mut camera, &collide_tilemap, &mut blend);
+    layers.set_pos(&mut vram, &camera);
+    world.frame(&actions, &object, &mut camera, &collide_tilemap, &mut blend, &mut palette_fader);
+    world.sync_tiles(&overlay, &mut collide_tilemap);
+    match camera.letterbox() {
+      Some(room) => {
+        windows.win_in(WinIn::Win0).set_position(&room).enable();
+        windows.win_out().enable();
+      }
+      None => {
+        windows.win_in(WinIn::Win0).disable();
+        windows.win_out().disable();
+      }
+    }
+    if playback.is_none() {
+      // Failed saves are retried every frame, so only a new error is logged
+      let save_error = world.store_requested_save(save_data.as_mut(), SAVE_SLOT).err();
+      if let Some(err) = save_error.filter(|err| Some(*err) != last_save_error) {
+        agb::println!("Couldn't save, retrying: {:?}", err);
+      }
+      last_save_error = save_error;
+    }
 
     vblank.wait_for_vblank();
-    primary.commit(&mut vram);
-    foreground.commit(&mut vram);
+    layers.redraw(&mut vram, &overlay);
+    layers.commit(&mut vram);
+    tile_animator.update(&mut vram);
+    palette_fader.commit(&mut vram);
     blend.commit();
+    windows.commit();
     mixer.frame();
     object.commit();
     input.update();
+
+    let held = match playback.as_mut().and_then(Iterator::next) {
+      Some(held) => held,
+      None => {
+        playback = None;
+        held_buttons(&input)
+      }
+    };
+    actions.update(&settings.bindings, held);
+    replay.record(held);
+    // L, R and Select stores everything pressed so far, to be played back with L and R at the next boot.
+    if input.is_pressed(Button::L) && input.is_pressed(Button::R) && input.is_just_pressed(Button::SELECT) {
+      if let Err(err) = save_data.as_mut().map_or(Err(SaveError::Media), |media| replay.store(media)) {
+        agb::println!("Couldn't store the replay: {:?}", err);
+      }
+    }
   }
+}
 
-  primary.clear(&mut vram);
-  foreground.clear(&mut vram);
-  loop {}
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use alloc::collections::BTreeMap;
+
+  // Host tests store saves in a buffer, this checks the real SRAM
+  #[test_case]
+  fn save_game_round_trips_through_sram(gba: &mut agb::Gba) {
+    let save = SaveGame {
+      room: GRAMBLES_ROOM,
+      checkpoint: Vector2D::new(PosNum::new(48), PosNum::new(96)),
+      abilities: 0b11,
+      play_time: 60 * 60,
+      tile_edits: BTreeMap::new(),
+    };
+    gba.save.init_sram();
+    let mut media = gba.save.access().unwrap();
+    save.store(&mut media, SAVE_SLOT).unwrap();
+    assert!(SaveGame::load(&mut media, SAVE_SLOT) == Ok(save));
+  }
 }
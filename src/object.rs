@@ -1,22 +1,67 @@
-use agb::{
-  fixnum::{Rect, Num},
-  display::blend::{Blend, Layer},
-};
+use alloc::vec::Vec;
+use agb_fixnum::{Rect, Num};
 use agb_ext::{
   math::PosNum,
 };
 use agb_ext::collision::{Pos, Size};
 use agb_ext::ecs::{Entity, HasEntity, MutEntityAccessor, Map};
 use agb_ext::math::ZERO;
-use crate::{Vector2D, World};
+use agb_fixnum::Vector2D;
+use crate::world::World;
+use crate::ability::{Ability, AbilityPickup};
 
 #[derive(Copy, Clone)]
 pub enum ObjectInit {
   ForegroundHide(i32, i32, i32, i32),
+  Checkpoint(i32, i32, i32, i32),
+  CameraBounds(i32, i32, i32, i32),
+  Hazard(i32, i32, i32, i32, i32),
+  Breakable(i32, i32, i32, i32, bool),
+  /// Target is an index into the room's objects
+  Switch(i32, i32, i32, i32, Option<usize>),
+  AbilityPickup(i32, i32, i32, i32, Ability),
+  PlayerStart(i32, i32),
+  /// Points in map coordinates, and whether the last point joins back to the first
+  Path(&'static [(i32, i32)], bool),
 }
 
 pub struct ForegroundHide;
 
+pub struct Checkpoint;
+
+/// Keeps the camera within this area while the player is inside it
+pub struct CameraBounds;
+
+pub struct Hazard {
+  pub damage: i32,
+}
+
+pub struct Breakable {
+  /// Whether the player breaks it by pushing against it, rather than only with a switch
+  pub touch: bool,
+}
+
+pub struct PlayerStart;
+
+pub struct Path {
+  pub points: &'static [(i32, i32)],
+  pub closed: bool,
+}
+
+pub struct Switch {
+  pub target: Option<Entity>,
+  pub pressed: bool,
+}
+
+/// Builds a room's objects, then resolves the references between them.
+pub fn build_objects(world: &mut World, objects: &[ObjectInit]) -> Vec<Entity> {
+  let entities: Vec<Entity> = objects.iter().map(|object| object.build(world)).collect();
+  for (object, en) in objects.iter().zip(&entities) {
+    object.link(world, *en, &entities);
+  }
+  entities
+}
+
 impl ObjectInit {
   pub fn build(self, world: &mut World) -> Entity {
     match self {
@@ -27,6 +72,65 @@ impl ObjectInit {
           .set(ForegroundHide)
           .entity()
       }
+      Self::Checkpoint(x, y, w, h) => {
+        world.build_entity()
+          .set(Pos((x, y).into()))
+          .set(Size((w, h).into()))
+          .set(Checkpoint)
+          .entity()
+      }
+      Self::CameraBounds(x, y, w, h) => {
+        world.build_entity()
+          .set(Pos((x, y).into()))
+          .set(Size((w, h).into()))
+          .set(CameraBounds)
+          .entity()
+      }
+      Self::Hazard(x, y, w, h, damage) => {
+        world.build_entity()
+          .set(Pos((x, y).into()))
+          .set(Size((w, h).into()))
+          .set(Hazard { damage })
+          .entity()
+      }
+      Self::Breakable(x, y, w, h, touch) => {
+        world.build_entity()
+          .set(Pos((x, y).into()))
+          .set(Size((w, h).into()))
+          .set(Breakable { touch })
+          .entity()
+      }
+      Self::Switch(x, y, w, h, _) => {
+        world.build_entity()
+          .set(Pos((x, y).into()))
+          .set(Size((w, h).into()))
+          .set(Switch { target: None, pressed: false })
+          .entity()
+      }
+      Self::AbilityPickup(x, y, w, h, ability) => {
+        world.build_entity()
+          .set(Pos((x, y).into()))
+          .set(Size((w, h).into()))
+          .set(AbilityPickup(ability))
+          .entity()
+      }
+      Self::PlayerStart(x, y) => {
+        world.build_entity()
+          .set(Pos((x, y).into()))
+          .set(PlayerStart)
+          .entity()
+      }
+      Self::Path(points, closed) => {
+        world.build_entity()
+          .set(Path { points, closed })
+          .entity()
+      }
+    }
+  }
+
+  fn link(self, world: &mut World, en: Entity, entities: &[Entity]) {
+    if let Self::Switch(.., Some(target)) = self {
+      world.entity_data_mut(en).set(Switch { target: entities.get(target).copied(), pressed: false });
     }
   }
 }
@@ -35,6 +139,7 @@ pub mod system {
   use agb_ext::blend::ManagedBlend;
   use super::*;
   use crate::player::CurrentPlayer;
+  use crate::health::Health;
 
   pub fn foreground_hide(_: &CurrentPlayer, current_player_en: &Entity, pos_map: &Map<Pos>, size_map: &Map<Size>, foreground_hide_map: &Map<ForegroundHide>, blend: &mut ManagedBlend) {
     if player_colliding(current_player_en, pos_map, size_map, foreground_hide_map.keys()).is_some() {
@@ -43,6 +148,61 @@ pub mod system {
       blend.min_inc_top_opacity();
     }
   }
+
+  pub fn checkpoint(_: &CurrentPlayer, current_player_en: &Entity, pos_map: &Map<Pos>, size_map: &Map<Size>, checkpoint_map: &Map<Checkpoint>) -> Option<Vector2D<PosNum>> {
+    let checkpoint_en = player_colliding(current_player_en, pos_map, size_map, checkpoint_map.keys())?;
+    let (checkpoint_pos, checkpoint_size) = (pos_map.get(&checkpoint_en)?, size_map.get(&checkpoint_en)?);
+    let player_size = size_map.get(current_player_en)?;
+    Some(Vector2D::new(checkpoint_pos.0.x, checkpoint_pos.0.y + checkpoint_size.0.y - player_size.0.y))
+  }
+
+  /// The camera bounds area containing the middle of the player, if any.
+  pub fn camera_bounds(_: &CurrentPlayer, current_player_en: &Entity, pos_map: &Map<Pos>, size_map: &Map<Size>, camera_bounds_map: &Map<CameraBounds>) -> Option<Rect<PosNum>> {
+    let (player_pos, player_size) = (pos_map.get(current_player_en)?, size_map.get(current_player_en)?);
+    let center = player_pos.0 + player_size.0 / PosNum::new(2);
+    camera_bounds_map.keys()
+      .filter_map(|bounds_en| Some(Rect::new(pos_map.get(bounds_en)?.0, size_map.get(bounds_en)?.0)))
+      .find(|bounds| bounds.contains_point(center))
+  }
+
+  pub fn ability_pickup(_: &CurrentPlayer, current_player_en: &Entity, pos_map: &Map<Pos>, size_map: &Map<Size>, pickup_map: &Map<AbilityPickup>) -> Option<(Entity, Ability)> {
+    let pickup_en = player_colliding(current_player_en, pos_map, size_map, pickup_map.keys())?;
+    Some((pickup_en, pickup_map.get(&pickup_en)?.0))
+  }
+
+  pub fn breakable(_: &CurrentPlayer, current_player_en: &Entity, pos_map: &Map<Pos>, size_map: &Map<Size>, breakable_map: &Map<Breakable>) -> Option<Entity> {
+    let (player_pos, player_size) = (pos_map.get(current_player_en)?, size_map.get(current_player_en)?);
+    // Breakables are solid, so grow the player by a pixel to catch them pressing against one
+    let one = PosNum::new(1);
+    let player_rect = Rect::new(player_pos.0 - (one, one).into(), player_size.0 + (one * 2, one * 2).into());
+    breakable_map.iter()
+      .find(|(breakable_en, breakable)| {
+        match (breakable.touch, pos_map.get(breakable_en), size_map.get(breakable_en)) {
+          (true, Some(pos), Some(size)) => player_rect.touches(Rect::new(pos.0, size.0)),
+          _ => false,
+        }
+      })
+      .map(|(breakable_en, _)| *breakable_en)
+  }
+
+  /// Presses a switch the player is touching, returning its target the first time.
+  pub fn switch(_: &CurrentPlayer, current_player_en: &Entity, pos_map: &Map<Pos>, size_map: &Map<Size>, switch_map: &mut Map<Switch>) -> Option<Entity> {
+    let switch_en = player_colliding(current_player_en, pos_map, size_map, switch_map.keys())?;
+    let switch = switch_map.get_mut(&switch_en)?;
+    if switch.pressed {
+      return None;
+    }
+    switch.pressed = true;
+    switch.target
+  }
+
+  pub fn hazard(_: &CurrentPlayer, current_player_en: &Entity, pos_map: &Map<Pos>, size_map: &Map<Size>, hazard_map: &Map<Hazard>, health: &mut Health) {
+    if let Some(hazard_en) = player_colliding(current_player_en, pos_map, size_map, hazard_map.keys()) {
+      if let Some(hazard) = hazard_map.get(&hazard_en) {
+        health.damage(hazard.damage);
+      }
+    }
+  }
 }
 
 fn player_colliding<'e>(current_player_en: &Entity, pos_map: &Map<Pos>, size_map: &Map<Size>, obj_iter: impl Iterator<Item=&'e Entity>) -> Option<Entity> {
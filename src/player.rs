@@ -1,61 +1,33 @@
-use agb::{
-  display::{
-    object::{Object, OamManaged},
-    Priority,
-  },
-  fixnum::{Vector2D, Rect, num},
-  input::{ButtonController, Button, Tri},
-};
+use agb_fixnum::{Vector2D, Rect, num};
 use agb_ext::{
+  hw::{Object, OamManaged, Priority, Tri},
   math::{PosNum, ZERO, const_num_i32},
   anim::{AnimPlayer, AnimOffset},
-  camera::Camera,
-  collision::{Entity, ControllableEntity, CollisionLayer, Acc, OnGround, Pos, Size, Vel},
+  camera::{Camera, FollowTarget},
+  collision::{Entity, ControllableEntity, CollisionLayer, CollideTilemap, CollideTileType, Acc, OnGround, WallContact, Pos, Size, Vel},
   ecs::{Entity as EcsEntity, MutEntityAccessor, HasEntity},
-  anim_enum,
 };
 use crate::world::{World};
-
-anim_enum!(AnimEnum {
-  Idle => 0,
-  RunLeadup => 1,
-  Run => 2
-});
+use crate::health::Health;
+use crate::ability::{Ability, Abilities, AirJump};
+use crate::action::{Action, Actions};
 
 mod gramble_sprites {
-  use agb::{
-    display::object::{Graphics, Tag},
-  };
-  use agb_ext::{
-    anim::Anim,
-    new_anim,
-  };
-  use agb_ext::anim::AnimId;
-  use super::AnimEnum;
-
-  static GRAPHICS: &Graphics = agb::include_aseprite!("gfx/gramble.aseprite");
-  static IDLE: &Tag = GRAPHICS.tags().get("Idle");
-
-  pub fn get_next_anim(anim_enum: AnimId) -> Anim {
-    match anim_enum.into() {
-      AnimEnum::Idle => new_anim!(IDLE, Some(AnimEnum::Idle.into()), (0, 30), (1, 5), (2, 5), (3, 30), (2, 5), (1, 5)),
-      AnimEnum::RunLeadup => new_anim!(IDLE, Some(AnimEnum::Run.into()), (0, 60)),
-      AnimEnum::Run => new_anim!(IDLE, Some(AnimEnum::Run.into()), (0, 60)),
-    }
-  }
+  use agb_ext::{hw::Graphics, include_aseprite};
+
+  static GRAPHICS: &Graphics = include_aseprite!("gfx/gramble.aseprite");
+  include!(concat!(env!("OUT_DIR"), "/gramble_anims.rs"));
 
   pub mod pipe {
     use super::*;
 
-    static GRAPHICS: &Graphics = agb::include_aseprite!("gfx/gramble_pipe.aseprite");
-    static IDLE: &Tag = GRAPHICS.tags().get("Idle");
-
-    pub fn get_next_anim(anim_enum: AnimId) -> Anim {
-      new_anim!(IDLE, Some(AnimEnum::Idle.into()), (0, 60))
-    }
+    static GRAPHICS: &Graphics = include_aseprite!("gfx/gramble_pipe.aseprite");
+    include!(concat!(env!("OUT_DIR"), "/gramble_pipe_anims.rs"));
   }
 }
 
+use gramble_sprites::AnimEnum;
+
 pub enum PlayerType {
   Gramble,
   Glyde,
@@ -63,6 +35,14 @@ pub enum PlayerType {
 
 pub struct CurrentPlayer;
 
+/// The contact and ability state a player's jumps depend on.
+pub struct Traversal<'a> {
+  pub on_ground: &'a OnGround,
+  pub wall_contact: Option<&'a WallContact>,
+  pub abilities: Option<&'a Abilities>,
+  pub air_jump: Option<&'a mut AirJump>,
+}
+
 pub struct GramblePipe<'obj> {
   anim: AnimPlayer<'obj>,
   position: Vector2D<PosNum>,
@@ -74,11 +54,22 @@ const GRAMBLE_MAX_VEL: PosNum = const_num_i32(3, 0);
 const GLYDE_MAX_VEL: PosNum = const_num_i32(2, 5);
 const GRAMBLE_MAX_HEIGHT: PosNum = const_num_i32(35, 0);
 const GLYDE_MAX_HEIGHT: PosNum = const_num_i32(19, 5);
+const GLIDE_FALL_VEL: PosNum = const_num_i32(0, 75);
+const WALL_SLIDE_FALL_VEL: PosNum = const_num_i32(1, 0);
+const WALL_JUMP_X_VEL: PosNum = const_num_i32(2, 5);
+const PIPE_SIZE: i32 = 16;
 
 fn jump_impulse(max_height: PosNum) -> PosNum {
   (PosNum::new(2) * GRAVITY * max_height).sqrt()
 }
 
+fn player_size(player_type: &PlayerType) -> Vector2D<PosNum> {
+  match player_type {
+    PlayerType::Gramble => (14, 28).into(),
+    PlayerType::Glyde => (24, 28).into(),
+  }
+}
+
 pub fn gramble<'obj>(world: &mut World<'obj>, object: &'obj OamManaged<'obj>, position: Vector2D<PosNum>) -> EcsEntity {
   let mut anim_player = AnimPlayer::new(object, gramble_sprites::get_next_anim, AnimEnum::Idle.into());
   anim_player.sprite_mut().set_priority(Priority::P2);
@@ -86,10 +77,14 @@ pub fn gramble<'obj>(world: &mut World<'obj>, object: &'obj OamManaged<'obj>, po
     .set(Pos(position))
     .set(Vel(Vector2D::new(ZERO, ZERO)))
     .set(Acc(Vector2D::new(ZERO, GRAVITY)))
-    .set(Size((14, 28).into()))
+    .set(Size(player_size(&PlayerType::Gramble)))
     .set(OnGround(false))
+    .set(WallContact::default())
     .set(CollisionLayer::Normal)
     .set(PlayerType::Gramble)
+    .set(Health::new(3))
+    .set(Abilities::none())
+    .set(AirJump(false))
     .set(anim_player)
     .set(AnimOffset((1, 4).into()))
     .entity()
@@ -100,10 +95,14 @@ pub fn glyde<'obj>(world: &mut World<'obj>, object: &'obj OamManaged, position:
     .set(Pos(position))
     .set(Vel(Vector2D::new(ZERO, ZERO)))
     .set(Acc(Vector2D::new(ZERO, GRAVITY)))
-    .set(Size((24, 28).into()))
+    .set(Size(player_size(&PlayerType::Glyde)))
     .set(OnGround(false))
+    .set(WallContact::default())
     .set(CollisionLayer::Normal)
     .set(PlayerType::Glyde)
+    .set(Health::new(3))
+    .set(Abilities::none())
+    .set(AirJump(false))
     .set(AnimPlayer::new(object, gramble_sprites::get_next_anim, AnimEnum::Idle.into()))
     .set(AnimOffset((4, 4).into()))
     .entity()
@@ -112,8 +111,8 @@ pub fn glyde<'obj>(world: &mut World<'obj>, object: &'obj OamManaged, position:
 pub mod system {
   use super::*;
 
-  pub fn run_anim<'o>(_: &PlayerType, anim: &mut AnimPlayer<'o>, current_player: Option<&CurrentPlayer>, object: &'o OamManaged, input: &ButtonController) {
-    let tri = input.x_tri();
+  pub fn run_anim<'o>(_: &PlayerType, anim: &mut AnimPlayer<'o>, current_player: Option<&CurrentPlayer>, object: &'o OamManaged, actions: &Actions) {
+    let tri = actions.tri(Action::Left, Action::Right);
     if current_player.is_some() && tri != Tri::Zero {
       match tri {
         Tri::Negative => {
@@ -132,19 +131,35 @@ pub mod system {
     }
   }
 
-  pub fn player_movement(player_type: &PlayerType, current_player: Option<&CurrentPlayer>, vel: &mut Vel, on_ground: &OnGround, input: &ButtonController) {
+  pub fn player_movement(player_type: &PlayerType, current_player: Option<&CurrentPlayer>, vel: &mut Vel, col_layer: &CollisionLayer, traversal: Traversal, actions: &Actions) {
+    let Traversal { on_ground, wall_contact, abilities, air_jump } = traversal;
     let max_velocity = match player_type {
       PlayerType::Gramble => GRAMBLE_MAX_VEL,
       PlayerType::Glyde => GLYDE_MAX_VEL,
     };
+    let has_ability = |ability| abilities.map_or(false, |abilities| abilities.has(ability));
 
     let tri = {
       if current_player.is_some() {
-        input.x_tri()
+        actions.tri(Action::Left, Action::Right)
       } else {
         Tri::Zero
       }
     };
+
+    if *col_layer == CollisionLayer::Pipe {
+      let y_tri = if current_player.is_some() { actions.tri(Action::Up, Action::Down) } else { Tri::Zero };
+      vel.0 = Vector2D::new(PosNum::new(tri as i32) * PIPE_MOVE_SPEED, PosNum::new(y_tri as i32) * PIPE_MOVE_SPEED);
+      return;
+    }
+
+    let mut air_jump = air_jump;
+    if on_ground.0 {
+      if let Some(air_jump) = air_jump.as_mut() {
+        air_jump.0 = true;
+      }
+    }
+
     let desired_x_vel = PosNum::new(tri as i32) * max_velocity;
     vel.0 = {
       let mut vel = vel.0.clone();
@@ -164,14 +179,34 @@ pub mod system {
       }
 
       if current_player.is_some() {
-        if input.is_just_pressed(Button::B) && on_ground.0 {
-          let jump_impulse = match player_type {
-            PlayerType::Gramble => jump_impulse(GRAMBLE_MAX_HEIGHT),
-            PlayerType::Glyde => jump_impulse(GLYDE_MAX_HEIGHT),
-          };
+        let jump_impulse = match player_type {
+          PlayerType::Gramble => jump_impulse(GRAMBLE_MAX_HEIGHT),
+          PlayerType::Glyde => jump_impulse(GLYDE_MAX_HEIGHT),
+        };
+        let can_air_jump = has_ability(Ability::DoubleJump) && air_jump.as_ref().map_or(false, |air_jump| air_jump.0);
+        let wall_side = match wall_contact {
+          Some(WallContact { left: true, .. }) if !on_ground.0 => Tri::Negative,
+          Some(WallContact { right: true, .. }) if !on_ground.0 => Tri::Positive,
+          _ => Tri::Zero,
+        };
+        if actions.is_just_pressed(Action::Jump) && on_ground.0 {
           vel.y = -jump_impulse;
-        } else if input.is_released(Button::B) {
+        } else if actions.is_just_pressed(Action::Jump) && wall_side != Tri::Zero && has_ability(Ability::WallJump) {
+          vel.y = -jump_impulse;
+          vel.x = -PosNum::new(wall_side as i32) * WALL_JUMP_X_VEL;
+        } else if actions.is_just_pressed(Action::Jump) && can_air_jump {
+          vel.y = -jump_impulse;
+          if let Some(air_jump) = air_jump {
+            air_jump.0 = false;
+          }
+        } else if actions.is_released(Action::Jump) {
           vel.y += GRAVITY * const_num_i32(0, 75);
+        } else if has_ability(Ability::Glide) && actions.is_pressed(Action::Jump) && vel.y > GLIDE_FALL_VEL {
+          vel.y = GLIDE_FALL_VEL;
+        }
+
+        if wall_side != Tri::Zero && tri == wall_side && vel.y > WALL_SLIDE_FALL_VEL {
+          vel.y = WALL_SLIDE_FALL_VEL;
         }
       }
 
@@ -182,8 +217,46 @@ pub mod system {
     }
   }
 
-  pub fn center_camera(_: &CurrentPlayer, pos: &Pos, size: &Size, camera: &mut Camera) {
-    camera.smoothed_center_on(pos.0 + (size.0 / const_num_i32(2, 0)));
+  pub fn pipe_entry(player_type: &PlayerType, abilities: &Abilities, col_layer: &mut CollisionLayer, pos: &mut Pos, size: &mut Size, actions: &Actions, tilemap: &CollideTilemap) {
+    match col_layer {
+      CollisionLayer::Normal => {
+        if !abilities.has(Ability::PipeEntry) || !actions.is_just_pressed(Action::Down) {
+          return;
+        }
+        let below = Vector2D::new(
+          (pos.0.x + size.0.x / const_num_i32(2, 0)).floor().div_euclid(16),
+          (pos.0.y + size.0.y).floor().div_euclid(16),
+        );
+        if tilemap.tile_at(below) == CollideTileType::PipeSolid {
+          *col_layer = CollisionLayer::Pipe;
+          pos.0 = (below * 16).into();
+          size.0 = (PIPE_SIZE, PIPE_SIZE).into();
+        }
+      }
+      CollisionLayer::Pipe => {
+        if !actions.is_just_pressed(Action::Up) {
+          return;
+        }
+        let normal_size = player_size(player_type);
+        let exit_pos = Vector2D::new(
+          pos.0.x + (size.0.x - normal_size.x) / const_num_i32(2, 0),
+          pos.0.y + size.0.y - normal_size.y - PosNum::new(PIPE_SIZE),
+        );
+        if tilemap.is_free(Rect::new(exit_pos, normal_size), CollisionLayer::Normal) {
+          *col_layer = CollisionLayer::Normal;
+          pos.0 = exit_pos;
+          size.0 = normal_size;
+        }
+      }
+    }
+  }
+
+  pub fn follow_camera(_: &CurrentPlayer, pos: &Pos, size: &Size, vel: &Vel, on_ground: &OnGround, camera: &mut Camera) {
+    camera.follow(FollowTarget {
+      center: pos.0 + (size.0 / const_num_i32(2, 0)),
+      direction: (vel.0.x < ZERO, vel.0.x > ZERO).into(),
+      on_ground: on_ground.0,
+    });
   }
 }
 
@@ -191,7 +264,7 @@ pub mod system {
 impl<'obj> GramblePipe<'obj> {
   pub fn new(object: &'obj OamManaged, position: Vector2D<PosNum>) -> Self {
     Self {
-      anim: AnimPlayer::new(object, gramble_sprites::pipe::get_next_anim, AnimEnum::Idle.into()),
+      anim: AnimPlayer::new(object, gramble_sprites::pipe::get_next_anim, gramble_sprites::pipe::AnimEnum::Idle.into()),
       position,
     }
   }
@@ -236,14 +309,41 @@ impl<'obj> Entity for GramblePipe<'obj> {
 }
 
 impl<'obj> ControllableEntity for GramblePipe<'obj> {
-  fn propose_movement(&mut self, input: Option<&ButtonController>) -> Vector2D<PosNum> {
-    if let Some(input) = input {
-      let x_tri = input.x_tri();
-      let y_tri = input.y_tri();
+  type Action = Action;
+
+  fn propose_movement(&mut self, actions: Option<&Actions>) -> Vector2D<PosNum> {
+    if let Some(actions) = actions {
+      let x_tri = actions.tri(Action::Left, Action::Right);
+      let y_tri = actions.tri(Action::Up, Action::Down);
 
       Vector2D::new(PosNum::new(x_tri as i32) * PIPE_MOVE_SPEED, PosNum::new(y_tri as i32) * PIPE_MOVE_SPEED)
     } else {
       Vector2D::new(ZERO, ZERO)
     }
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use agb_ext::{hw::Button, rle::RleLayer};
+  use crate::action::ActionBindings;
+
+  // 2x2 map with a pipe top in its bottom right, placed left of the world origin
+  static DATA: [u8; 6] = [2, 0, 1, 0, 1, 8];
+
+  #[test]
+  fn pipe_entry_finds_pipes_left_of_the_origin() {
+    let tilemap = CollideTilemap::new(RleLayer::new(&DATA, &[0, 2], 1), 2, 2).with_origin((-2, 0).into());
+    let mut abilities = Abilities::none();
+    abilities.grant(Ability::PipeEntry);
+    let mut actions = Actions::new();
+    actions.update(&ActionBindings::new(), Button::DOWN);
+    let mut col_layer = CollisionLayer::Normal;
+    let mut pos = Pos((-15, -12).into());
+    let mut size = Size(player_size(&PlayerType::Gramble));
+    system::pipe_entry(&PlayerType::Gramble, &abilities, &mut col_layer, &mut pos, &mut size, &actions, &tilemap);
+    assert!(col_layer == CollisionLayer::Pipe);
+    assert!(pos.0 == (-16, 16).into());
+  }
+}
@@ -0,0 +1,156 @@
+//! Everything pressed since boot, along with the save the session started from and the button bindings in use, so it
+//! can be stored to SRAM and played back frame for frame on a later boot.
+use alloc::vec::Vec;
+use agb_ext::{
+  hw::Button,
+  input::{InputRecording, InputPlayback},
+  save::{SaveSlots, SaveMedia, SaveRecord, SaveError, ByteWriter, ByteReader},
+};
+use crate::save::{SaveGame, SAVE_SLOTS};
+use crate::settings::Settings;
+
+pub const REPLAY_SLOTS: SaveSlots = SaveSlots::new(1, 16 * 1024).starting_at(SAVE_SLOTS.end());
+const REPLAY_SLOT: usize = 0;
+const REPLAY_VERSION: u16 = 2;
+/// Room for one more run, so `record` stops before the encoding outgrows the slot.
+const RUN_LEN: usize = 4;
+
+#[derive(Clone, PartialEq)]
+pub struct Replay {
+  start: Option<SaveRecord>,
+  settings: SaveRecord,
+  input: InputRecording,
+}
+
+impl Replay {
+  pub fn new(start: Option<&SaveGame>, settings: &Settings) -> Self {
+    Replay { start: start.map(SaveGame::to_record), settings: settings.to_record(), input: InputRecording::new() }
+  }
+
+  pub fn start(&self) -> Option<SaveGame> {
+    self.start.as_ref().and_then(|record| SaveGame::decode(record).ok())
+  }
+
+  pub fn settings(&self) -> Settings {
+    Settings::decode(&self.settings).unwrap_or_default()
+  }
+
+  pub fn input(&self) -> &InputRecording {
+    &self.input
+  }
+
+  /// Records one frame of input, returning false once the replay is too long to store.
+  pub fn record(&mut self, held: Button) -> bool {
+    if self.encoded_len() + RUN_LEN > REPLAY_SLOTS.max_payload_len() {
+      return false;
+    }
+    self.input.push(held);
+    true
+  }
+
+  pub fn into_playback(self) -> InputPlayback {
+    InputPlayback::new(self.input)
+  }
+
+  pub fn load(media: &mut impl SaveMedia) -> Result<Self, SaveError> {
+    let record = REPLAY_SLOTS.load(media, REPLAY_SLOT)?;
+    match record.version {
+      1..=REPLAY_VERSION => Self::decode(&record.payload, record.version),
+      version => Err(SaveError::UnsupportedVersion(version)),
+    }
+  }
+
+  pub fn store(&self, media: &mut impl SaveMedia) -> Result<(), SaveError> {
+    REPLAY_SLOTS.store(media, REPLAY_SLOT, REPLAY_VERSION, &self.encode())
+  }
+
+  fn encoded_len(&self) -> usize {
+    1 + self.start.as_ref().map_or(0, |start| 4 + start.payload.len()) + 4 + self.settings.payload.len() + self.input.encoded_len()
+  }
+
+  fn encode(&self) -> Vec<u8> {
+    let mut writer = ByteWriter::new();
+    match &self.start {
+      Some(start) => {
+        writer.u8(1);
+        encode_record(&mut writer, start);
+      }
+      None => {
+        writer.u8(0);
+      }
+    }
+    encode_record(&mut writer, &self.settings);
+    self.input.encode(&mut writer);
+    writer.finish()
+  }
+
+  fn decode(payload: &[u8], version: u16) -> Result<Self, SaveError> {
+    let mut reader = ByteReader::new(payload);
+    let start = match reader.u8()? {
+      0 => None,
+      1 => Some(decode_record(&mut reader)?),
+      _ => return Err(SaveError::Corrupt),
+    };
+    let settings = if version >= 2 { decode_record(&mut reader)? } else { Settings::default().to_record() };
+    Ok(Replay { start, settings, input: InputRecording::decode(&mut reader)? })
+  }
+}
+
+fn encode_record(writer: &mut ByteWriter, record: &SaveRecord) {
+  writer.u16(record.version).u16(record.payload.len() as u16);
+  for byte in &record.payload {
+    writer.u8(*byte);
+  }
+}
+
+fn decode_record(reader: &mut ByteReader) -> Result<SaveRecord, SaveError> {
+  let version = reader.u16()?;
+  let payload = (0..reader.u16()?).map(|_| reader.u8()).collect::<Result<_, _>>()?;
+  Ok(SaveRecord { version, payload })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use alloc::collections::BTreeMap;
+  use agb_fixnum::Vector2D;
+  use agb_ext::{math::PosNum, save::BufferMedia};
+  use crate::action::Action;
+
+  #[test]
+  fn replay_round_trips_after_the_save_slots() {
+    let save = SaveGame {
+      room: 0,
+      checkpoint: Vector2D::new(PosNum::new(48), PosNum::new(96)),
+      abilities: 0b1,
+      play_time: 600,
+      tile_edits: BTreeMap::new(),
+    };
+    let mut settings = Settings::default();
+    settings.bindings.bind(Action::Jump, Button::A);
+    let mut replay = Replay::new(Some(&save), &settings);
+    for held in [Button::empty(), Button::RIGHT, Button::RIGHT, Button::RIGHT | Button::B] {
+      assert!(replay.record(held));
+    }
+
+    let mut data = [0xFF; 32 * 1024];
+    let mut media = BufferMedia(&mut data);
+    save.store(&mut media, 2).unwrap();
+    replay.store(&mut media).unwrap();
+    assert!(SaveGame::load(&mut media, 2) == Ok(save.clone()));
+    let loaded = Replay::load(&mut media).unwrap();
+    assert!(loaded == replay);
+    assert!(loaded.start() == Some(save));
+    assert_eq!(loaded.settings(), settings);
+  }
+
+  #[test]
+  fn stops_recording_when_full() {
+    let mut replay = Replay::new(None, &Settings::default());
+    let mut held = Button::A;
+    while replay.record(held) {
+      held = if held == Button::A { Button::B } else { Button::A };
+    }
+    assert!(replay.encode().len() <= REPLAY_SLOTS.max_payload_len());
+  }
+}
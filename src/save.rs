@@ -0,0 +1,145 @@
+use alloc::{vec::Vec, collections::BTreeMap};
+use agb_fixnum::Vector2D;
+use agb_ext::{
+  math::PosNum,
+  save::{SaveSlots, SaveMedia, SaveRecord, SaveError, ByteWriter, ByteReader},
+  overlay::{TileEdit, TilemapLayer, FlipTile},
+  collision::CollideTileType,
+};
+
+pub const SAVE_SLOTS: SaveSlots = SaveSlots::new(3, 1024);
+const SAVE_VERSION: u16 = 3;
+
+#[derive(Clone, PartialEq)]
+pub struct SaveGame {
+  pub room: u16,
+  pub checkpoint: Vector2D<PosNum>,
+  pub abilities: u32,
+  pub play_time: u32,
+  pub tile_edits: BTreeMap<u16, Vec<TileEdit>>,
+}
+
+impl SaveGame {
+  pub fn load(media: &mut impl SaveMedia, slot: usize) -> Result<Self, SaveError> {
+    Self::decode(&SAVE_SLOTS.load(media, slot)?)
+  }
+
+  pub fn store(&self, media: &mut impl SaveMedia, slot: usize) -> Result<(), SaveError> {
+    SAVE_SLOTS.store(media, slot, SAVE_VERSION, &self.encode())
+  }
+
+  pub fn to_record(&self) -> SaveRecord {
+    SaveRecord { version: SAVE_VERSION, payload: self.encode() }
+  }
+
+  pub fn encode(&self) -> Vec<u8> {
+    let mut writer = ByteWriter::new();
+    writer
+      .u16(self.room)
+      .i32(self.checkpoint.x.to_raw())
+      .i32(self.checkpoint.y.to_raw())
+      .u32(self.abilities)
+      .u32(self.play_time);
+    writer.u16(self.tile_edits.len() as u16);
+    for (room, edits) in &self.tile_edits {
+      writer.u16(*room).u16(edits.len() as u16);
+      for edit in edits {
+        Self::encode_tile_edit(&mut writer, edit);
+      }
+    }
+    writer.finish()
+  }
+
+  pub fn decode(record: &SaveRecord) -> Result<Self, SaveError> {
+    let mut reader = ByteReader::new(&record.payload);
+    match record.version {
+      1..=3 => Ok(Self {
+        room: reader.u16()?,
+        checkpoint: Vector2D::new(PosNum::from_raw(reader.i32()?), PosNum::from_raw(reader.i32()?)),
+        abilities: reader.u32()?,
+        play_time: reader.u32()?,
+        tile_edits: if record.version >= 2 { Self::decode_tile_edits(&mut reader, record.version)? } else { BTreeMap::new() },
+      }),
+      version => Err(SaveError::UnsupportedVersion(version)),
+    }
+  }
+
+  fn encode_tile_edit(writer: &mut ByteWriter, edit: &TileEdit) {
+    writer
+      .u8(edit.layer as u8)
+      .u16(edit.pos.x as u16)
+      .u16(edit.pos.y as u16)
+      .u16(edit.metatile.idx())
+      .u8(edit.metatile.flip_bits())
+      .u8(edit.collision.map_or(u8::MAX, |collision| collision as u8));
+  }
+
+  fn decode_tile_edits(reader: &mut ByteReader, version: u16) -> Result<BTreeMap<u16, Vec<TileEdit>>, SaveError> {
+    let mut tile_edits = BTreeMap::new();
+    for _ in 0..reader.u16()? {
+      let room = reader.u16()?;
+      let edits = (0..reader.u16()?)
+        .map(|_| Self::decode_tile_edit(reader, version))
+        .collect::<Result<Vec<_>, _>>()?;
+      tile_edits.insert(room, edits);
+    }
+    Ok(tile_edits)
+  }
+
+  fn decode_tile_edit(reader: &mut ByteReader, version: u16) -> Result<TileEdit, SaveError> {
+    let layer = match reader.u8()? {
+      0 => TilemapLayer::Background,
+      1 => TilemapLayer::Primary,
+      2 => TilemapLayer::Foreground,
+      _ => return Err(SaveError::Corrupt),
+    };
+    let pos = Vector2D::new(reader.u16()? as i32, reader.u16()? as i32);
+    // Version 2 stored metatile indices in a single byte
+    let idx = if version >= 3 { reader.u16()? } else { reader.u8()? as u16 };
+    let metatile = FlipTile::from_flip_bits(idx, reader.u8()?).ok_or(SaveError::Corrupt)?;
+    let collision = match reader.u8()? {
+      u8::MAX => None,
+      value => Some(CollideTileType::from_u8(value).ok_or(SaveError::Corrupt)?),
+    };
+    Ok(TileEdit { layer, pos, metatile, collision })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use agb_ext::save::BufferMedia;
+
+  #[test]
+  fn save_game_round_trips() {
+    let save = SaveGame {
+      room: 2,
+      checkpoint: Vector2D::new(PosNum::new(48), PosNum::new(96) + PosNum::from_raw(3)),
+      abilities: 0b101,
+      play_time: 60 * 60 * 3,
+      tile_edits: BTreeMap::from([(0, alloc::vec![
+        TileEdit { layer: TilemapLayer::Primary, pos: Vector2D::new(12, 5), metatile: FlipTile::N(0), collision: Some(CollideTileType::Pass) },
+        TileEdit { layer: TilemapLayer::Foreground, pos: Vector2D::new(3, 9), metatile: FlipTile::XY(300), collision: None },
+      ])]),
+    };
+    let mut data = [0xFF; 1024 * 3];
+    let mut media = BufferMedia(&mut data);
+    save.store(&mut media, 1).unwrap();
+    assert!(SaveGame::load(&mut media, 1) == Ok(save));
+  }
+
+  #[test]
+  fn loads_saves_without_tile_edits() {
+    let mut writer = ByteWriter::new();
+    writer.u16(1).i32(0).i32(0).u32(0).u32(120);
+    let record = SaveRecord { version: 1, payload: writer.finish() };
+    let save = SaveGame::decode(&record).unwrap();
+    assert!(save.room == 1 && save.play_time == 120 && save.tile_edits.is_empty());
+  }
+
+  #[test]
+  fn rejects_unknown_versions() {
+    let record = SaveRecord { version: SAVE_VERSION + 1, payload: Vec::new() };
+    assert!(SaveGame::decode(&record) == Err(SaveError::UnsupportedVersion(SAVE_VERSION + 1)));
+  }
+}
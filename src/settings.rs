@@ -0,0 +1,86 @@
+//! Player preferences, kept apart from the save slots so they apply whichever game is loaded.
+use alloc::vec::Vec;
+use agb_ext::{
+  hw::Button,
+  input::Action as _,
+  save::{SaveSlots, SaveMedia, SaveRecord, SaveError, ByteWriter, ByteReader},
+};
+use crate::action::{Action, ActionBindings};
+use crate::replay::REPLAY_SLOTS;
+
+pub const SETTINGS_SLOTS: SaveSlots = SaveSlots::new(1, 256).starting_at(REPLAY_SLOTS.end());
+const SETTINGS_SLOT: usize = 0;
+const SETTINGS_VERSION: u16 = 1;
+
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Settings {
+  pub bindings: ActionBindings,
+}
+
+impl Settings {
+  pub fn load(media: &mut impl SaveMedia) -> Result<Self, SaveError> {
+    Self::decode(&SETTINGS_SLOTS.load(media, SETTINGS_SLOT)?)
+  }
+
+  pub fn store(&self, media: &mut impl SaveMedia) -> Result<(), SaveError> {
+    SETTINGS_SLOTS.store(media, SETTINGS_SLOT, SETTINGS_VERSION, &self.encode())
+  }
+
+  pub fn to_record(&self) -> SaveRecord {
+    SaveRecord { version: SETTINGS_VERSION, payload: self.encode() }
+  }
+
+  pub fn encode(&self) -> Vec<u8> {
+    let mut writer = ByteWriter::new();
+    writer.u8(Action::ALL.len() as u8);
+    for &action in Action::ALL {
+      writer.u16(self.bindings.chord(action).bits() as u16);
+    }
+    writer.finish()
+  }
+
+  /// Actions added since the settings were stored keep their default chord.
+  pub fn decode(record: &SaveRecord) -> Result<Self, SaveError> {
+    let mut reader = ByteReader::new(&record.payload);
+    match record.version {
+      1 => {
+        let mut settings = Settings::default();
+        for index in 0..reader.u8()? as usize {
+          let chord = Button::from_bits_truncate(reader.u16()? as u32);
+          if let Some(&action) = Action::ALL.get(index) {
+            settings.bindings.bind(action, chord);
+          }
+        }
+        Ok(settings)
+      }
+      version => Err(SaveError::UnsupportedVersion(version)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use agb_ext::save::BufferMedia;
+
+  #[test]
+  fn remapped_bindings_round_trip() {
+    let mut settings = Settings::default();
+    settings.bindings.bind(Action::Jump, Button::A);
+    settings.bindings.bind(Action::Dash, Button::L | Button::R);
+    let mut data = [0xFF; 32 * 1024];
+    let mut media = BufferMedia(&mut data);
+    assert_eq!(Settings::load(&mut media), Err(SaveError::Empty));
+    settings.store(&mut media).unwrap();
+    assert_eq!(Settings::load(&mut media), Ok(settings));
+  }
+
+  #[test]
+  fn new_actions_keep_their_defaults() {
+    let mut writer = ByteWriter::new();
+    writer.u8(1).u16(Button::RIGHT.bits() as u16);
+    let settings = Settings::decode(&SaveRecord { version: 1, payload: writer.finish() }).unwrap();
+    assert_eq!(settings.bindings.chord(Action::Left), Button::RIGHT);
+    assert_eq!(settings.bindings.chord(Action::Jump), Button::B);
+  }
+}
@@ -0,0 +1,131 @@
+//! Runs the world off-hardware against agb-ext's headless stand-ins, driven by scripted or recorded input. Each frame
+//! the player's physics state is traced so replays can be compared between builds, see `tests/replay.rs`.
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use agb_fixnum::Vector2D;
+use agb_ext::{
+  hw::{OamManaged, Button, Blend},
+  input::InputRecording,
+  blend::ManagedBlend,
+  palette::PaletteFader,
+  camera::Camera,
+  collision::{CollideTilemap, Pos, Vel, OnGround},
+  ecs::{Entity, EntityAccessor},
+  math::PosNum,
+  overlay::TileOverlay,
+};
+use crate::world::{World, RoomLoader};
+use crate::player::gramble;
+use crate::action::{Actions, ActionBindings};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TraceFrame {
+  pub pos: Vector2D<PosNum>,
+  pub vel: Vector2D<PosNum>,
+  pub on_ground: bool,
+}
+
+pub struct Simulation<'o> {
+  world: World<'o>,
+  object: &'o OamManaged<'o>,
+  bindings: ActionBindings,
+  actions: Actions,
+  camera: Camera,
+  collide_tilemap: CollideTilemap,
+  overlay: TileOverlay,
+  blend: ManagedBlend<'o>,
+  palette_fader: PaletteFader,
+  player: Entity,
+  trace: Vec<TraceFrame>,
+  recording: InputRecording,
+}
+
+impl<'o> Simulation<'o> {
+  /// Loads a room and spawns Gramble at its `PlayerStart`, the same way `main` does.
+  pub fn new(object: &'o OamManaged<'o>, room: u16, room_loader: RoomLoader<'o>, collide_tilemap: CollideTilemap) -> Self {
+    let mut world = World::new();
+    world.load_room(room, room_loader);
+    let player_start = world.player_start().expect("Simulated rooms need a PlayerStart object");
+    let player = gramble(&mut world, object, player_start);
+    world.set_checkpoint(player_start);
+
+    let mut camera = Camera::new();
+    camera.set_limits(collide_tilemap.bounds());
+    let overlay = TileOverlay::new();
+    let mut collide_tilemap = collide_tilemap;
+    world.sync_tiles(&overlay, &mut collide_tilemap);
+
+    Simulation {
+      world,
+      object,
+      bindings: ActionBindings::new(),
+      actions: Actions::new(),
+      camera,
+      collide_tilemap,
+      overlay,
+      blend: ManagedBlend::new(Blend::new()),
+      palette_fader: PaletteFader::new(&[]),
+      player,
+      trace: Vec::new(),
+      recording: InputRecording::new(),
+    }
+  }
+
+  /// Runs one frame with `buttons` held, tracing the player afterwards.
+  pub fn frame(&mut self, buttons: Button) {
+    self.actions.update(&self.bindings, buttons);
+    self.recording.push(buttons);
+    self.world.frame(&self.actions, self.object, &mut self.camera, &self.collide_tilemap, &mut self.blend, &mut self.palette_fader);
+    self.world.sync_tiles(&self.overlay, &mut self.collide_tilemap);
+    self.trace.push(self.trace_frame());
+  }
+
+  /// Runs a script of `(frames, buttons)` steps, holding each step's buttons for its number of frames.
+  pub fn run(&mut self, script: &[(u32, Button)]) {
+    for &(frames, buttons) in script {
+      for _ in 0..frames {
+        self.frame(buttons);
+      }
+    }
+  }
+
+  /// Plays back a recording made on device or by another simulation.
+  pub fn play(&mut self, recording: &InputRecording) {
+    for buttons in recording.iter() {
+      self.frame(buttons);
+    }
+  }
+
+  /// The default bindings, for tests that remap buttons before running.
+  pub fn bindings_mut(&mut self) -> &mut ActionBindings {
+    &mut self.bindings
+  }
+
+  pub fn trace(&self) -> &[TraceFrame] {
+    &self.trace
+  }
+
+  /// Everything this simulation has been fed so far.
+  pub fn recording(&self) -> &InputRecording {
+    &self.recording
+  }
+
+  pub fn world(&self) -> &World<'o> {
+    &self.world
+  }
+
+  fn trace_frame(&self) -> TraceFrame {
+    let player = self.world.entity_data(self.player);
+    TraceFrame {
+      pos: EntityAccessor::<Pos>::get(&player).map_or(Vector2D::default(), |pos| pos.0),
+      vel: EntityAccessor::<Vel>::get(&player).map_or(Vector2D::default(), |vel| vel.0),
+      on_ground: EntityAccessor::<OnGround>::get(&player).map_or(false, |on_ground| on_ground.0),
+    }
+  }
+}
+
+impl Display for TraceFrame {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    write!(f, "pos ({}, {}) vel ({}, {}) on_ground {}", self.pos.x, self.pos.y, self.vel.x, self.vel.y, self.on_ground)
+  }
+}
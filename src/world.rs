@@ -1,36 +1,289 @@
-use agb::display::blend::Blend;
-use agb::display::object::OamManaged;
-use agb::display::tiled::VRamManager;
-use agb::input::ButtonController;
-use agb::sound::mixer::Mixer;
+use agb_fixnum::Vector2D;
 use agb_ext::{
-  collision::{Pos, Vel, Acc, OnGround, Size, system as colsys},
+  hw::OamManaged,
+  collision::{Pos, Vel, Acc, OnGround, WallContact, Size, system as colsys},
   ecs::{Entity, Entities, Map, EntityAccessor, MutEntityAccessor, HasEntity},
-  anim::{AnimOffset, AnimPlayer, system as anisys},
+  anim::{AnimEvents, AnimOffset, AnimPlayer, system as anisys},
 };
 use agb_ext::blend::ManagedBlend;
+use agb_ext::palette::{PaletteFader, FadeColour};
 use agb_ext::camera::Camera;
-use agb_ext::collision::{CollideTilemap, CollisionLayer};
+use agb_ext::collision::{CollideTilemap, CollideTileType, CollisionLayer};
+use agb_ext::math::{PosNum, ZERO, MIN_INC, const_num_i32};
+use agb_ext::overlay::{TileEdit, TileOverlay, TilemapLayer, FlipTile};
+use agb_ext::save::{SaveError, SaveMedia};
+#[cfg(feature = "agb")]
 use agb_ext::tiles::Tilemap;
+use alloc::{vec::Vec, collections::BTreeMap};
 use crate::{
-  player::{PlayerType, CurrentPlayer, system as playersys},
-  object::{ForegroundHide, system as objsys},
+  player::{PlayerType, CurrentPlayer, Traversal, system as playersys},
+  object::{ForegroundHide, Checkpoint, Hazard, Breakable, Switch, PlayerStart, Path, CameraBounds, system as objsys},
+  health::{Health, Respawn, DeathState, system as healthsys},
+  save::SaveGame,
+  ability::{Abilities, AbilityPickup, AirJump},
+  action::Actions,
 };
 
-type Components<'o> = (Map<Pos>, Map<Vel>, Map<Acc>, Map<Size>, Map<OnGround>, Map<CollisionLayer>, Map<PlayerType>, Map<AnimPlayer<'o>>, Map<AnimOffset>, Map<ForegroundHide>);
+type Components<'o> = (Map<Pos>, Map<Vel>, Map<Acc>, Map<Size>, Map<OnGround>, Map<CollisionLayer>, Map<PlayerType>, Map<AnimPlayer<'o>>, Map<AnimOffset>, Map<ForegroundHide>, Map<Health>, Map<Checkpoint>, Map<Hazard>, Map<Abilities>, Map<AbilityPickup>, Map<AirJump>, Map<WallContact>, Map<Breakable>, Map<Switch>, Map<PlayerStart>, Map<Path>, Map<CameraBounds>);
+
+const DAMAGE_TRAUMA: PosNum = const_num_i32(0, 5);
+const BREAK_TRAUMA: PosNum = const_num_i32(0, 3);
+/// Switches show the wall they open before handing the camera back.
+const SWITCH_PAN_SPEED: PosNum = const_num_i32(4, 0);
+const SWITCH_PAN_HOLD_FRAMES: u16 = 45;
+
+pub type RoomLoader<'o> = fn(&mut World<'o>) -> Vec<Entity>;
+
+/// Everything needed to enter a room, looked up by the room id saves store.
+pub struct Room {
+  #[cfg(feature = "agb")]
+  pub tilemap: &'static Tilemap,
+  pub load_objects: for<'o> fn(&mut World<'o>) -> Vec<Entity>,
+}
 
 pub struct World<'o> {
   pub(self) components: Components<'o>,
   entities: Entities,
   next_entity_id: i32,
+  room: u16,
+  room_loader: Option<RoomLoader<'o>>,
+  room_entities: Vec<Entity>,
+  respawn: Respawn,
+  play_time: u32,
+  save_requested: bool,
+  tile_edits: BTreeMap<u16, Vec<TileEdit>>,
+  pending_tile_edits: Vec<TileEdit>,
+  reset_tiles: bool,
+  /// Events from the animation frames started this frame, for sound and effects to follow.
+  anim_events: Vec<(Entity, AnimEvents)>,
 }
 
 impl<'o> World<'o> {
   pub fn new() -> Self {
     World {
-      components: (Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), ),
+      components: (Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), Map::new(), ),
       entities: Map::new(),
       next_entity_id: 0,
+      room: 0,
+      room_loader: None,
+      room_entities: Vec::new(),
+      respawn: Respawn::new(Vector2D::new(ZERO, ZERO)),
+      play_time: 0,
+      save_requested: false,
+      tile_edits: BTreeMap::new(),
+      pending_tile_edits: Vec::new(),
+      reset_tiles: false,
+      anim_events: Vec::new(),
+    }
+  }
+
+  pub fn remove_entity(&mut self, en: Entity) {
+    self.entities.remove(&en);
+    self.components.0.remove(&en);
+    self.components.1.remove(&en);
+    self.components.2.remove(&en);
+    self.components.3.remove(&en);
+    self.components.4.remove(&en);
+    self.components.5.remove(&en);
+    self.components.6.remove(&en);
+    self.components.7.remove(&en);
+    self.components.8.remove(&en);
+    self.components.9.remove(&en);
+    self.components.10.remove(&en);
+    self.components.11.remove(&en);
+    self.components.12.remove(&en);
+    self.components.13.remove(&en);
+    self.components.14.remove(&en);
+    self.components.15.remove(&en);
+    self.components.16.remove(&en);
+    self.components.17.remove(&en);
+    self.components.18.remove(&en);
+    self.components.19.remove(&en);
+    self.components.20.remove(&en);
+    self.components.21.remove(&en);
+  }
+
+  pub fn load_room(&mut self, room: u16, room_loader: RoomLoader<'o>) {
+    for en in core::mem::take(&mut self.room_entities) {
+      self.remove_entity(en);
+    }
+    self.room = room;
+    self.room_loader = Some(room_loader);
+    self.room_entities = room_loader(self);
+    self.despawn_collected_pickups();
+    self.reset_tiles = true;
+    self.pending_tile_edits = self.tile_edits.get(&room).cloned().unwrap_or_default();
+    self.despawn_broken_walls();
+  }
+
+  /// Changes a metatile of the current room, remembering it so it survives reloading the room and saving.
+  pub fn edit_tile(&mut self, edit: TileEdit) {
+    let room_edits = self.tile_edits.entry(self.room).or_default();
+    room_edits.retain(|old| old.layer != edit.layer || old.pos != edit.pos);
+    room_edits.push(edit);
+    self.pending_tile_edits.push(edit);
+    self.save_requested = true;
+  }
+
+  pub fn sync_tiles(&mut self, overlay: &TileOverlay, collide_tilemap: &mut CollideTilemap) {
+    if core::mem::take(&mut self.reset_tiles) {
+      overlay.clear(collide_tilemap);
+    }
+    for edit in self.pending_tile_edits.drain(..) {
+      overlay.apply(collide_tilemap, edit);
+    }
+  }
+
+  fn break_wall(&mut self, en: Entity) {
+    if !self.components.17.contains_key(&en) {
+      return;
+    }
+    for pos in self.breakable_metatiles(&en) {
+      self.edit_tile(TileEdit {
+        layer: TilemapLayer::Primary,
+        pos,
+        metatile: FlipTile::N(0),
+        collision: Some(CollideTileType::Pass),
+      });
+    }
+    self.remove_entity(en);
+  }
+
+  fn breakable_metatiles(&self, en: &Entity) -> Vec<Vector2D<i32>> {
+    let (Some(pos), Some(size)) = (self.components.0.get(en), self.components.3.get(en)) else {
+      return Vec::new();
+    };
+    let metatile_size = PosNum::new(16);
+    let (left, top) = ((pos.0.x / metatile_size).floor(), (pos.0.y / metatile_size).floor());
+    let (right, bottom) = (((pos.0.x + size.0.x - MIN_INC) / metatile_size).floor(), ((pos.0.y + size.0.y - MIN_INC) / metatile_size).floor());
+    (top..=bottom).flat_map(|y| (left..=right).map(move |x| Vector2D::new(x, y))).collect()
+  }
+
+  fn despawn_broken_walls(&mut self) {
+    let room_edits = self.tile_edits.get(&self.room);
+    let broken: Vec<Entity> = self.components.17.keys()
+      .filter(|en| {
+        let metatiles = self.breakable_metatiles(en);
+        room_edits.is_some_and(|edits| metatiles.iter().all(|pos| edits.iter().any(|edit| edit.layer == TilemapLayer::Primary && edit.pos == *pos)))
+      })
+      .copied()
+      .collect();
+    for en in broken {
+      self.remove_entity(en);
+    }
+  }
+
+  fn despawn_collected_pickups(&mut self) {
+    let collected: Vec<Entity> = self.components.14.iter()
+      .filter(|(_, pickup)| self.components.13.values().any(|abilities| abilities.has(pickup.0)))
+      .map(|(en, _)| *en)
+      .collect();
+    for en in collected {
+      self.remove_entity(en);
+    }
+  }
+
+  /// Where the current room's `PlayerStart` object is, if it has one.
+  pub fn player_start(&self) -> Option<Vector2D<PosNum>> {
+    self.components.19.keys().find_map(|en| self.components.0.get(en)).map(|pos| pos.0)
+  }
+
+  pub fn set_checkpoint(&mut self, checkpoint: Vector2D<PosNum>) {
+    self.respawn.checkpoint = checkpoint;
+  }
+
+  pub fn save_game(&self) -> SaveGame {
+    SaveGame {
+      room: self.room,
+      checkpoint: self.respawn.checkpoint,
+      abilities: self.components.13.values().next().copied().unwrap_or_default().bits(),
+      play_time: self.play_time,
+      tile_edits: self.tile_edits.clone(),
+    }
+  }
+
+  /// Restores a save, loading its room from `rooms`, which is indexed by room id. A save from a room that isn't in
+  /// `rooms` is rejected, leaving the world as it was.
+  #[must_use]
+  pub fn apply_save(&mut self, save: &SaveGame, rooms: &[Room]) -> bool {
+    let Some(room) = rooms.get(save.room as usize) else {
+      return false;
+    };
+    self.room = save.room;
+    self.room_loader = Some(room.load_objects);
+    for abilities in self.components.13.values_mut() {
+      *abilities = Abilities::from_bits(save.abilities);
+    }
+    self.play_time = save.play_time;
+    self.tile_edits = save.tile_edits.clone();
+    self.respawn.checkpoint = save.checkpoint;
+    self.respawn_at_checkpoint();
+    true
+  }
+
+  pub fn room(&self) -> u16 {
+    self.room
+  }
+
+  pub fn anim_events(&self) -> &[(Entity, AnimEvents)] {
+    &self.anim_events
+  }
+
+  /// Stores the game if a checkpoint asked for it since the last successful save. A failed save stays requested, so
+  /// calling this every frame retries it.
+  pub fn store_requested_save(&mut self, media: Option<&mut impl SaveMedia>, slot: usize) -> Result<(), SaveError> {
+    if !self.save_requested {
+      return Ok(());
+    }
+    self.save_game().store(media.ok_or(SaveError::Media)?, slot)?;
+    self.save_requested = false;
+    Ok(())
+  }
+
+  fn respawn_at_checkpoint(&mut self) {
+    if let Some(room_loader) = self.room_loader {
+      self.load_room(self.room, room_loader);
+    }
+    for (en, _) in self.components.6.iter() {
+      if let Some(pos) = self.components.0.get_mut(en) {
+        pos.0 = self.respawn.checkpoint;
+      }
+      if let Some(vel) = self.components.1.get_mut(en) {
+        vel.0 = Vector2D::new(ZERO, ZERO);
+      }
+      if let Some(on_ground) = self.components.4.get_mut(en) {
+        on_ground.0 = false;
+      }
+      if let Some(health) = self.components.10.get_mut(en) {
+        health.restore();
+      }
+    }
+  }
+
+  fn death_frame(&mut self, palette_fader: &mut PaletteFader) -> bool {
+    match self.respawn.state {
+      DeathState::Alive => {
+        let dead = self.components.10.values().any(|health| health.is_dead());
+        if dead {
+          self.respawn.state = DeathState::FadingOut;
+        }
+        dead
+      }
+      DeathState::FadingOut => {
+        palette_fader.min_inc_fade(FadeColour::Black);
+        if palette_fader.is_faded_out() {
+          self.respawn_at_checkpoint();
+          self.respawn.state = DeathState::FadingIn;
+        }
+        true
+      }
+      DeathState::FadingIn => {
+        palette_fader.min_dec_fade();
+        if palette_fader.is_faded_in() {
+          self.respawn.state = DeathState::Alive;
+        }
+        false
+      }
     }
   }
 
@@ -58,21 +311,41 @@ impl<'o> World<'o> {
     }
   }
 
-  pub fn frame(&mut self, input: &ButtonController, object: &'o OamManaged<'o>, camera: &mut Camera, collide_tilemap: &CollideTilemap, blend: &mut ManagedBlend) {
+  pub fn frame(&mut self, actions: &Actions, object: &'o OamManaged<'o>, camera: &mut Camera, collide_tilemap: &CollideTilemap, blend: &mut ManagedBlend, palette_fader: &mut PaletteFader) {
+    self.play_time = self.play_time.saturating_add(1);
+    self.anim_events.clear();
+    camera.tick();
+    if self.death_frame(palette_fader) {
+      for (en, player) in self.components.7.iter_mut() {
+        if let Some(pos) = self.components.0.get(en) {
+          anisys::position_anim(player, pos, self.components.8.get(en), &camera);
+        }
+      }
+      return;
+    }
+
     for (en, vel) in self.components.1.iter_mut() {
       if let Some(acc) = self.components.2.get(en) {
         colsys::apply_acc(vel, acc);
       }
       let (pos, size) = (self.components.0.get(en), self.components.3.get(en));
       if let (Some(player_type), Some(on_ground)) = (self.components.6.get(en), self.components.4.get(en)) {
-        playersys::player_movement(player_type, Some(&CurrentPlayer), vel, on_ground, input);
+        let col_layer = self.components.5.get(en).copied().unwrap_or(CollisionLayer::Normal);
+        let traversal = Traversal {
+          on_ground,
+          wall_contact: self.components.16.get(en),
+          abilities: self.components.13.get(en),
+          air_jump: self.components.15.get_mut(en),
+        };
+        playersys::player_movement(player_type, Some(&CurrentPlayer), vel, &col_layer, traversal, actions);
         if let (Some(pos), Some(size)) = (pos, size) {
-          playersys::center_camera(&CurrentPlayer, pos, size, camera);
+          camera.set_area_limits(objsys::camera_bounds(&CurrentPlayer, en, &self.components.0, &self.components.3, &self.components.21));
+          playersys::follow_camera(&CurrentPlayer, pos, size, vel, on_ground, camera);
         }
       }
       if let (Some(pos), Some(size), Some(col_layer)) =
       (pos, size, self.components.5.get(en)) {
-        colsys::physics_process(pos, vel, size, col_layer, self.components.4.get_mut(en), &collide_tilemap);
+        colsys::physics_process(pos, vel, size, col_layer, self.components.4.get_mut(en), self.components.16.get_mut(en), &collide_tilemap);
       }
     }
     for (en, pos) in self.components.0.iter_mut() {
@@ -84,13 +357,67 @@ impl<'o> World<'o> {
       if let Some(pos) = self.components.0.get(en) {
         anisys::position_anim(player, pos, self.components.8.get(en), &camera);
       }
-      anisys::draw(player, &object);
+      let events = anisys::draw(player, &object);
+      if !events.is_empty() {
+        self.anim_events.push((*en, events));
+      }
     }
+    let mut collected_pickups = Vec::new();
+    let mut broken_walls = Vec::new();
     for (en, player_type) in self.components.6.iter() {
       if let Some(anim) = self.components.7.get_mut(en) {
-        playersys::run_anim(player_type, anim, Some(&CurrentPlayer), object, input);
+        playersys::run_anim(player_type, anim, Some(&CurrentPlayer), object, actions);
+      }
+      if let (Some(abilities), Some(col_layer), Some(pos), Some(size)) =
+      (self.components.13.get(en), self.components.5.get_mut(en), self.components.0.get_mut(en), self.components.3.get_mut(en)) {
+        playersys::pipe_entry(player_type, abilities, col_layer, pos, size, actions, collide_tilemap);
+      }
+      if let Some((pickup_en, ability)) = objsys::ability_pickup(&CurrentPlayer, en, &self.components.0, &self.components.3, &self.components.14) {
+        if let Some(abilities) = self.components.13.get_mut(en) {
+          abilities.grant(ability);
+        }
+        collected_pickups.push(pickup_en);
+      }
+      if let Some(breakable_en) = objsys::breakable(&CurrentPlayer, en, &self.components.0, &self.components.3, &self.components.17) {
+        broken_walls.push(breakable_en);
+      }
+      if let Some(target_en) = objsys::switch(&CurrentPlayer, en, &self.components.0, &self.components.3, &mut self.components.18) {
+        if let (Some(pos), Some(size)) = (self.components.0.get(&target_en), self.components.3.get(&target_en)) {
+          camera.pan_to(pos.0 + size.0 / PosNum::new(2), SWITCH_PAN_SPEED, SWITCH_PAN_HOLD_FRAMES);
+        }
+        broken_walls.push(target_en);
       }
       objsys::foreground_hide(&CurrentPlayer, en, &self.components.0, &self.components.3, &self.components.9, blend);
+      if let Some(checkpoint) = objsys::checkpoint(&CurrentPlayer, en, &self.components.0, &self.components.3, &self.components.11) {
+        if checkpoint != self.respawn.checkpoint {
+          self.respawn.checkpoint = checkpoint;
+          self.save_requested = true;
+        }
+      }
+      if let Some(health) = self.components.10.get_mut(en) {
+        healthsys::tick_invulnerability(health);
+        healthsys::damage_flash(health, palette_fader);
+        let was_invulnerable = health.is_invulnerable();
+        objsys::hazard(&CurrentPlayer, en, &self.components.0, &self.components.3, &self.components.12, health);
+        if health.is_invulnerable() && !was_invulnerable {
+          camera.add_trauma(DAMAGE_TRAUMA);
+        }
+        if let Some(pos) = self.components.0.get(en) {
+          healthsys::fall_out_of_map(pos, health, collide_tilemap);
+        }
+      }
+    }
+    if !collected_pickups.is_empty() {
+      for en in collected_pickups {
+        self.remove_entity(en);
+      }
+      self.save_requested = true;
+    }
+    for en in broken_walls {
+      if self.components.17.contains_key(&en) {
+        camera.add_trauma(BREAK_TRAUMA);
+      }
+      self.break_wall(en);
     }
   }
 }
@@ -139,3 +466,76 @@ impl_entity_accessor!(PlayerType, 6);
 impl_entity_accessor!(AnimPlayer<'o>, 7);
 impl_entity_accessor!(AnimOffset, 8);
 impl_entity_accessor!(ForegroundHide, 9);
+impl_entity_accessor!(Health, 10);
+impl_entity_accessor!(Checkpoint, 11);
+impl_entity_accessor!(Hazard, 12);
+impl_entity_accessor!(Abilities, 13);
+impl_entity_accessor!(AbilityPickup, 14);
+impl_entity_accessor!(AirJump, 15);
+impl_entity_accessor!(WallContact, 16);
+impl_entity_accessor!(Breakable, 17);
+impl_entity_accessor!(Switch, 18);
+impl_entity_accessor!(PlayerStart, 19);
+impl_entity_accessor!(Path, 20);
+impl_entity_accessor!(CameraBounds, 21);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use agb_ext::save::BufferMedia;
+  use crate::object::{build_objects, ObjectInit};
+
+  fn room_a(world: &mut World) -> Vec<Entity> {
+    build_objects(world, &[ObjectInit::PlayerStart(16, 32)])
+  }
+
+  fn room_b(world: &mut World) -> Vec<Entity> {
+    build_objects(world, &[ObjectInit::PlayerStart(80, 48)])
+  }
+
+  fn save_in(room: u16) -> SaveGame {
+    SaveGame {
+      room,
+      checkpoint: Vector2D::new(PosNum::new(80), PosNum::new(48)),
+      abilities: 0,
+      play_time: 120,
+      tile_edits: BTreeMap::new(),
+    }
+  }
+
+  #[test]
+  fn save_loads_its_own_room() {
+    let mut world = World::new();
+    let rooms = [Room { load_objects: room_a }, Room { load_objects: room_b }];
+    world.load_room(0, room_a);
+    assert!(world.apply_save(&save_in(1), &rooms));
+    assert_eq!(world.room(), 1);
+    assert_eq!(world.player_start(), Some(Vector2D::new(PosNum::new(80), PosNum::new(48))));
+    assert_eq!(world.components.19.len(), 1);
+  }
+
+  #[test]
+  fn failed_saves_stay_requested() {
+    let mut world = World::new();
+    world.load_room(0, room_a);
+    world.save_requested = true;
+    let mut full = [0xFF; 8];
+    assert!(world.store_requested_save(Some(&mut BufferMedia(&mut full)), 0).is_err());
+    assert!(world.store_requested_save(None::<&mut BufferMedia>, 0) == Err(SaveError::Media));
+    let mut data = [0xFF; 1024 * 3];
+    assert!(world.store_requested_save(Some(&mut BufferMedia(&mut data)), 0) == Ok(()));
+    assert!(!world.save_requested);
+    assert!(SaveGame::load(&mut BufferMedia(&mut data), 0).is_ok_and(|save| save.room == 0));
+  }
+
+  #[test]
+  fn save_from_unknown_room_is_rejected() {
+    let mut world = World::new();
+    let rooms = [Room { load_objects: room_a }, Room { load_objects: room_b }];
+    world.load_room(0, room_a);
+    assert!(!world.apply_save(&save_in(2), &rooms));
+    assert_eq!(world.room(), 0);
+    assert_eq!(world.save_game().play_time, 0);
+    assert_eq!(world.player_start(), Some(Vector2D::new(PosNum::new(16), PosNum::new(32))));
+  }
+}
@@ -0,0 +1,103 @@
+#![cfg(not(feature = "agb"))]
+
+use std::fs;
+use agb_ext::{
+  hw::{Button, OamManaged},
+  input::InputRecording,
+  save::{ByteWriter, ByteReader},
+};
+use gramble_and_glyde::{grambles_room, sim::Simulation, action::Action, GRAMBLES_ROOM};
+
+const TRACES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/traces");
+
+/// Compares against `tests/traces/{name}.txt`, one line per frame. Run with `UPDATE_TRACES=1` to accept changes.
+fn assert_trace(name: &str, sim: &Simulation) {
+  let trace: String = sim.trace().iter()
+    .enumerate()
+    .map(|(frame, trace)| format!("{frame}: {trace}\n"))
+    .collect();
+  let path = format!("{TRACES_DIR}/{name}.txt");
+  if std::env::var_os("UPDATE_TRACES").is_some() {
+    fs::write(&path, &trace).unwrap();
+    return;
+  }
+  let expected = fs::read_to_string(&path)
+    .unwrap_or_else(|_| panic!("Missing trace {path}, run with UPDATE_TRACES=1 to create it"));
+  if let Some((frame, (actual, expected))) = trace.lines().zip(expected.lines()).enumerate().find(|(_, (actual, expected))| actual != expected) {
+    panic!("Trace {name} diverges at frame {frame}:\n  expected {expected}\n  actual   {actual}");
+  }
+  assert_eq!(trace.lines().count(), expected.lines().count(), "Trace {name} has a different number of frames");
+}
+
+fn grambles_room<'o>(object: &'o OamManaged<'o>) -> Simulation<'o> {
+  Simulation::new(object, GRAMBLES_ROOM, grambles_room::load_objects, grambles_room::collide_tilemap())
+}
+
+#[test]
+fn standing_still_falls_to_the_floor() {
+  let object = OamManaged::new();
+  let mut sim = grambles_room(&object);
+  sim.run(&[(60, Button::empty())]);
+  assert!(sim.trace().last().unwrap().on_ground);
+  assert_trace("standing_still", &sim);
+}
+
+#[test]
+fn running_jump() {
+  let object = OamManaged::new();
+  let mut sim = grambles_room(&object);
+  sim.run(&[
+    (30, Button::empty()),
+    (20, Button::RIGHT),
+    (15, Button::RIGHT | Button::B),
+    (30, Button::RIGHT),
+    (20, Button::empty()),
+  ]);
+  assert_trace("running_jump", &sim);
+}
+
+#[test]
+fn short_hop_is_lower_than_a_held_jump() {
+  let object = OamManaged::new();
+  let peak = |script: &[(u32, Button)]| {
+    let mut sim = grambles_room(&object);
+    sim.run(&[(30, Button::empty())]);
+    sim.run(script);
+    sim.trace().iter().map(|frame| frame.pos.y).min().unwrap()
+  };
+  let held = peak(&[(40, Button::B)]);
+  let tapped = peak(&[(2, Button::B), (38, Button::empty())]);
+  assert!(held < tapped, "held jump peaked at {held}, tapped at {tapped}");
+}
+
+#[test]
+fn recorded_input_replays_frame_exact() {
+  let object = OamManaged::new();
+  let mut recorded = grambles_room(&object);
+  recorded.run(&[
+    (10, Button::empty()),
+    (25, Button::LEFT),
+    (3, Button::LEFT | Button::B),
+    (12, Button::B),
+    (20, Button::RIGHT),
+  ]);
+  let mut writer = ByteWriter::new();
+  recorded.recording().encode(&mut writer);
+  let bytes = writer.finish();
+  let recording = InputRecording::decode(&mut ByteReader::new(&bytes)).unwrap();
+
+  let mut replayed = grambles_room(&object);
+  replayed.play(&recording);
+  assert_eq!(replayed.trace(), recorded.trace());
+}
+
+#[test]
+fn remapped_jump_matches_the_default_binding() {
+  let object = OamManaged::new();
+  let mut default = grambles_room(&object);
+  default.run(&[(30, Button::empty()), (20, Button::B)]);
+  let mut remapped = grambles_room(&object);
+  remapped.bindings_mut().bind(Action::Jump, Button::A);
+  remapped.run(&[(30, Button::empty()), (20, Button::A)]);
+  assert_eq!(remapped.trace(), default.trace());
+}
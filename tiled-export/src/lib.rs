@@ -0,0 +1,666 @@
+use std::fmt::{Display, Formatter};
+use tiled::{Loader, Map, Tileset, LayerType, TileLayer, LayerTileData, TileId, ObjectShape, PropertyValue};
+use std::fs::File;
+use std::io::{BufWriter, Result, Write};
+use agb_ext::math::frames_from_ms;
+
+/// Each tileset is written to `{module}.rs` in `OUT_DIR` and needs a matching `mod` in main.rs.
+pub const TILESETS: &[TilesetConfig] = &[
+  TilesetConfig { module: "tileset", tiles: "tileset", metatiles: "metatileset", index_width: IndexWidth::U8 },
+];
+
+pub const LEVELS: &[LevelConfig] = &[
+  LevelConfig { name: "grambles_room", tileset: "tileset", palettes: None, origin: (0, 0) },
+  LevelConfig { name: "slope_test", tileset: "tileset", palettes: None, origin: (0, 0) },
+];
+
+const CLEAR_COLOR: &str = "333333";
+const TILE_LAYERS: &[&str] = &["Primary", "Background", "Foreground", "Collision"];
+const COLLISION_TILESET: &str = "collision";
+const ABILITIES: &[&str] = &["DoubleJump", "PipeEntry", "Glide", "WallJump"];
+
+/// Object types and the custom properties each one reads, in the order they're passed to its `ObjectInit` variant
+/// after the object's shape.
+pub const OBJECT_TYPES: &[ObjectType] = &[
+  ObjectType { name: "PlayerStart", shape: Shape::Point, properties: &[] },
+  ObjectType { name: "Path", shape: Shape::Path, properties: &[] },
+  ObjectType { name: "ForegroundHide", shape: Shape::Area, properties: &[] },
+  ObjectType { name: "Checkpoint", shape: Shape::Area, properties: &[] },
+  ObjectType { name: "CameraBounds", shape: Shape::Area, properties: &[] },
+  ObjectType { name: "Hazard", shape: Shape::Area, properties: &[
+    Property { name: "damage", kind: PropertyKind::Int, default: Some("1") },
+  ] },
+  ObjectType { name: "Breakable", shape: Shape::Area, properties: &[
+    Property { name: "touch", kind: PropertyKind::Bool, default: Some("true") },
+  ] },
+  ObjectType { name: "Switch", shape: Shape::Area, properties: &[
+    Property { name: "target", kind: PropertyKind::Object, default: Some("None") },
+  ] },
+  ObjectType { name: "AbilityPickup", shape: Shape::Area, properties: &[
+    Property { name: "ability", kind: PropertyKind::Enum { path: "crate::ability::Ability", variants: ABILITIES }, default: None },
+  ] },
+];
+
+pub struct ObjectType {
+  pub name: &'static str,
+  pub shape: Shape,
+  pub properties: &'static [Property],
+}
+
+pub enum Shape {
+  /// Rectangles and ellipses, passed as their bounding box `x, y, w, h`.
+  Area,
+  /// Passed as `x, y`.
+  Point,
+  /// Polylines and polygons, passed as their points in map coordinates and whether the path is closed.
+  Path,
+}
+
+pub struct Property {
+  pub name: &'static str,
+  pub kind: PropertyKind,
+  /// Rust expression used when an object doesn't set the property. Required properties have none.
+  pub default: Option<&'static str>,
+}
+
+/// Object properties become `Option<usize>` indices into the level's `OBJECTS`.
+pub enum PropertyKind {
+  Int,
+  Bool,
+  String,
+  Enum { path: &'static str, variants: &'static [&'static str] },
+  Object,
+}
+
+/// Collects every problem found in a map file so they can all be reported before failing the build.
+struct Diagnostics {
+  path: String,
+  errors: Vec<String>,
+}
+
+impl Diagnostics {
+  fn new(path: &str) -> Self {
+    Diagnostics { path: path.to_string(), errors: vec![] }
+  }
+
+  fn error(&mut self, message: impl Display) {
+    self.errors.push(message.to_string());
+  }
+
+  fn layer_error(&mut self, layer: &str, message: impl Display) {
+    self.error(format!("layer \"{layer}\": {message}"));
+  }
+
+  fn tile_error(&mut self, layer: &str, x: i32, y: i32, message: impl Display) {
+    self.error(format!("layer \"{layer}\" at tile ({x}, {y}): {message}"));
+  }
+
+  fn object_error(&mut self, obj: &tiled::ObjectData, message: impl Display) {
+    self.error(format!("object {} \"{}\" of type \"{}\": {message}", obj.id(), obj.name, obj.user_type));
+  }
+
+  fn finish(self) {
+    if !self.errors.is_empty() {
+      for error in &self.errors {
+        println!("cargo::warning={}: {error}", self.path);
+      }
+      panic!("{} has {} error(s):\n  {}", self.path, self.errors.len(), self.errors.join("\n  "));
+    }
+  }
+}
+
+/// Exports every tileset and level above from `maps_dir` into `out_dir`.
+pub fn export_all(maps_dir: &str, out_dir: &str) -> Result<()> {
+  let mut loader = Loader::new();
+
+  let mut metatile_counts = vec![];
+  for tileset in TILESETS {
+    metatile_counts.push(export_tileset(tileset, maps_dir, out_dir, &mut loader)?);
+  }
+  for level in LEVELS {
+    let tileset_idx = TILESETS.iter()
+      .position(|tileset| tileset.module == level.tileset)
+      .unwrap_or_else(|| panic!("Level {} uses unknown tileset {}", level.name, level.tileset));
+    export_level(level, &TILESETS[tileset_idx], metatile_counts[tileset_idx], maps_dir, out_dir, &mut loader)?;
+  }
+  Ok(())
+}
+
+fn load_map(loader: &mut Loader, path: &str) -> Map {
+  println!("cargo::rerun-if-changed={path}");
+  loader.load_tmx_map(path).unwrap_or_else(|err| panic!("{path}: couldn't load map: {err}"))
+}
+
+fn load_tileset(loader: &mut Loader, path: &str) -> Tileset {
+  println!("cargo::rerun-if-changed={path}");
+  loader.load_tsx_tileset(path).unwrap_or_else(|err| panic!("{path}: couldn't load tileset: {err}"))
+}
+
+/// Bytes per metatile index in exported levels. Only metatilesets with more than 255 metatiles need `U16`.
+#[derive(Clone, Copy)]
+pub enum IndexWidth {
+  U8,
+  U16,
+}
+
+pub struct TilesetConfig {
+  pub module: &'static str,
+  pub tiles: &'static str,
+  pub metatiles: &'static str,
+  pub index_width: IndexWidth,
+}
+
+pub struct LevelConfig {
+  pub name: &'static str,
+  pub tileset: &'static str,
+  /// Path to a `&'static [Palette16]` replacing the tileset's palettes for this level.
+  pub palettes: Option<&'static str>,
+  /// The world metatile the map's top left corner is placed at. Objects are exported in world pixels.
+  pub origin: (i32, i32),
+}
+
+impl IndexWidth {
+  fn max_index(self) -> u32 {
+    match self {
+      Self::U8 => u8::MAX as u32,
+      Self::U16 => u16::MAX as u32,
+    }
+  }
+
+  fn bytes(self, idx: u32) -> Vec<u8> {
+    match self {
+      Self::U8 => vec![idx as u8],
+      Self::U16 => (idx as u16).to_le_bytes().to_vec(),
+    }
+  }
+}
+
+struct DeserializedFlipTile {
+  tile_id: TileId,
+  suffix: &'static str,
+  flip: &'static str,
+}
+
+impl Display for DeserializedFlipTile {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "FlipTile::{}({}{})", self.flip, self.tile_id, self.suffix)
+  }
+}
+
+/// Returns the number of metatiles, which levels using this tileset are validated against.
+pub fn export_tileset(config: &TilesetConfig, maps_dir: &str, out_dir: &str, loader: &mut Loader) -> Result<usize> {
+  let (tileset, metatileset) = (config.tiles, config.metatiles);
+  let tileset = load_tileset(loader, &format!("{maps_dir}/{tileset}.tsx"));
+  let metatileset_tsx = load_tileset(loader, &format!("{maps_dir}/{metatileset}.tsx"));
+  let metatileset = load_map(loader, &format!("{maps_dir}/{metatileset}.tmx"));
+
+  let output_file = File::create(format!("{out_dir}/{}.rs", config.module))?;
+  let mut writer = BufWriter::new(output_file);
+
+  let image_source = {
+    if let Some(image) = &tileset.image {
+      image.source
+        .clone()
+        .into_os_string()
+        .into_string()
+        .expect("Couldn't convert image path to String.")
+        .replace("\\", "/")
+    } else {
+      panic!("No single image defined for base tileset");
+    }
+  };
+  writeln!(&mut writer, r#"
+  use agb_ext::tiles::{{TileSetData, Metatile, FlipTile, TileAnimation}};
+
+  agb::include_background_gfx!(tileset, "{CLEAR_COLOR}", background => "{image_source}");
+  "#)?;
+
+  let mut metatile_quadrants = vec![];
+  if let Some(LayerType::Tiles(tile_layer)) = metatileset.layers().next().map(|l| l.layer_type()) {
+    if let TileLayer::Finite(layer) = tile_layer {
+      writeln!(&mut writer, "const METATILES: &[Metatile] = &[")?;
+      for yi in (0..layer.height()).step_by(2) {
+        for xi in (0..layer.width()).step_by(2) {
+          let xi = xi as i32;
+          let yi = yi as i32;
+          let quadrants = [(xi, yi), (xi + 1, yi), (xi, yi + 1), (xi + 1, yi + 1)]
+            .map(|(x, y)| layer.get_tile_data(x, y).map(|tile| (tile.id(), tile.flip_h, tile.flip_v)));
+          let ul = get_tile_id(layer.get_tile_data(xi, yi));
+          let ur = get_tile_id(layer.get_tile_data(xi + 1, yi));
+          let ll = get_tile_id(layer.get_tile_data(xi, yi + 1));
+          let lr = get_tile_id(layer.get_tile_data(xi + 1, yi + 1));
+
+          writeln!(&mut writer, "Metatile::new({ul},{ur},{ll},{lr}),")?;
+          metatile_quadrants.push(quadrants);
+        }
+      }
+      writeln!(&mut writer, "];")?;
+    } else {
+      panic!("Infinite tile layer not supported for metatileset");
+    }
+  } else {
+    panic!("Non-tile type layer not supported for metatileset")
+  }
+
+  if metatile_quadrants.len() as u32 > config.index_width.max_index() {
+    panic!("Metatileset {} has {} metatiles, too many for tileset {}'s index width", config.metatiles, metatile_quadrants.len(), config.module);
+  }
+
+  let mut animations: Vec<(TileId, Vec<(TileId, u32)>)> = vec![];
+  for (tile_id, tile) in tileset.tiles() {
+    if let Some(frames) = &tile.animation {
+      animations.push((tile_id, frames.iter().map(|frame| (frame.tile_id, frame.duration)).collect()));
+    }
+  }
+  for (metatile_id, metatile) in metatileset_tsx.tiles() {
+    let Some(frames) = &metatile.animation else { continue };
+    let base = metatile_quadrants.get(metatile_id as usize)
+      .unwrap_or_else(|| panic!("Animated metatile {metatile_id} is outside the metatileset map"));
+    for (quadrant, base_tile) in base.iter().enumerate() {
+      let Some((base_id, base_flip_h, base_flip_v)) = base_tile else { continue };
+      let mut tile_frames = vec![];
+      for frame in frames {
+        match metatile_quadrants.get(frame.tile_id as usize).and_then(|frame_quadrants| frame_quadrants[quadrant]) {
+          Some((frame_id, flip_h, flip_v)) if flip_h == *base_flip_h && flip_v == *base_flip_v => {
+            tile_frames.push((frame_id, frame.duration));
+          }
+          _ => panic!("Animated metatile {metatile_id} frame {} must use tiles with the same flips in each corner", frame.tile_id),
+        }
+      }
+      if animations.iter().all(|(tile_id, _)| tile_id != base_id) {
+        animations.push((*base_id, tile_frames));
+      }
+    }
+  }
+
+  writeln!(&mut writer, "const ANIMATIONS: &[TileAnimation] = &[")?;
+  for (tile_id, frames) in animations {
+    write!(&mut writer, "TileAnimation {{ tile: {tile_id}, frames: &[")?;
+    for (frame_id, duration_ms) in frames {
+      let duration = frames_from_ms(duration_ms).clamp(1, u16::MAX as u32);
+      write!(&mut writer, "agb_ext::tiles::AnimatedTileFrame {{ tile: {frame_id}, duration: {duration} }},")?;
+    }
+    writeln!(&mut writer, "] }},")?;
+  }
+  writeln!(&mut writer, "];")?;
+
+  writeln!(
+    &mut writer,
+    r#"
+    pub static TILESET_DATA: TileSetData = TileSetData{{
+      metatiles: METATILES,
+      palettes: tileset::PALETTES,
+      tile_data: &tileset::background,
+      animations: ANIMATIONS,
+    }};
+    "#
+  )?;
+
+  Ok(metatile_quadrants.len())
+}
+
+fn validate_level(path: &str, map: &Map, tileset: &TilesetConfig, metatile_count: usize) {
+  let mut diagnostics = Diagnostics::new(path);
+  let object_ids: Vec<u32> = map_objects(map).iter().map(|obj| obj.id()).collect();
+  if !map.tilesets().iter().any(|map_tileset| map_tileset.name == tileset.metatiles) {
+    diagnostics.error(format!("doesn't use metatileset \"{}\" of tileset \"{}\"", tileset.metatiles, tileset.module));
+  }
+  if metatile_count == 0 {
+    diagnostics.error(format!("metatileset \"{}\" has no metatiles", tileset.metatiles));
+  }
+
+  let mut seen_layers: Vec<String> = vec![];
+  for layer in map.layers() {
+    let name = layer.name.as_str();
+    let tile_layer = match layer.layer_type() {
+      LayerType::Tiles(TileLayer::Finite(tile_layer)) => tile_layer,
+      LayerType::Tiles(TileLayer::Infinite(_)) => {
+        diagnostics.layer_error(name, "infinite tile layers aren't supported");
+        continue;
+      }
+      LayerType::Objects(obj_layer) => {
+        for obj in obj_layer.objects() {
+          validate_object(&mut diagnostics, &obj, &object_ids);
+        }
+        continue;
+      }
+      _ => continue,
+    };
+
+    if !TILE_LAYERS.contains(&name) {
+      diagnostics.layer_error(name, format!("unknown tile layer, expected one of {}", TILE_LAYERS.join(", ")));
+      continue;
+    }
+    if seen_layers.iter().any(|seen| seen == name) {
+      diagnostics.layer_error(name, "defined more than once");
+    }
+    seen_layers.push(name.to_string());
+    if (tile_layer.width(), tile_layer.height()) != (map.width, map.height) {
+      diagnostics.layer_error(name, format!(
+        "is {}x{} but the map is {}x{}", tile_layer.width(), tile_layer.height(), map.width, map.height
+      ));
+    }
+
+    let (expected_tileset, usable_tiles) = if name == "Collision" {
+      (COLLISION_TILESET, 11)
+    } else {
+      (tileset.metatiles, metatile_count as u32)
+    };
+    for yi in 0..tile_layer.height() as i32 {
+      for xi in 0..tile_layer.width() as i32 {
+        let Some(tile) = tile_layer.get_tile_data(xi, yi) else { continue };
+        let tile_tileset = &map.tilesets()[tile.tileset_index()].name;
+        if tile_tileset != expected_tileset {
+          diagnostics.tile_error(name, xi, yi, format!("uses a tile from \"{tile_tileset}\" instead of \"{expected_tileset}\""));
+        } else if tile.id() >= usable_tiles {
+          diagnostics.tile_error(name, xi, yi, format!("tile id {} is out of range, \"{expected_tileset}\" has {usable_tiles} usable tiles", tile.id()));
+        }
+      }
+    }
+  }
+  for required in ["Primary", "Collision"] {
+    if !seen_layers.iter().any(|seen| seen == required) {
+      diagnostics.error(format!("missing required \"{required}\" tile layer"));
+    }
+  }
+
+  diagnostics.finish();
+}
+
+fn validate_object(diagnostics: &mut Diagnostics, obj: &tiled::ObjectData, object_ids: &[u32]) {
+  let Some(object_type) = object_type(&obj.user_type) else {
+    let names: Vec<&str> = OBJECT_TYPES.iter().map(|object_type| object_type.name).collect();
+    diagnostics.object_error(obj, format!("unknown object type, expected one of {}", names.join(", ")));
+    return;
+  };
+  match (&object_type.shape, &obj.shape) {
+    (Shape::Area, ObjectShape::Rect { .. } | ObjectShape::Ellipse { .. }) => {}
+    (Shape::Point, ObjectShape::Point(..)) => {}
+    (Shape::Path, ObjectShape::Polyline { .. } | ObjectShape::Polygon { .. }) => {}
+    (Shape::Area, _) => diagnostics.object_error(obj, "must be a rectangle or ellipse"),
+    (Shape::Point, _) => diagnostics.object_error(obj, "must be a point"),
+    (Shape::Path, _) => diagnostics.object_error(obj, "must be a polyline or polygon"),
+  }
+
+  for name in obj.properties.keys() {
+    if !object_type.properties.iter().any(|property| property.name == name) {
+      diagnostics.object_error(obj, format!("unknown property \"{name}\""));
+    }
+  }
+  for property in object_type.properties {
+    match obj.properties.get(property.name) {
+      Some(value) => {
+        if let Err(message) = property_value(property, value, object_ids) {
+          diagnostics.object_error(obj, format!("property \"{}\" {message}", property.name));
+        }
+      }
+      None if property.default.is_none() => {
+        diagnostics.object_error(obj, format!("missing required property \"{}\"", property.name));
+      }
+      None => {}
+    }
+  }
+}
+
+fn object_type(name: &str) -> Option<&'static ObjectType> {
+  OBJECT_TYPES.iter().find(|object_type| object_type.name == name)
+}
+
+/// Every object in the map, in the order they're exported to `OBJECTS`.
+fn map_objects(map: &Map) -> Vec<tiled::Object<'_>> {
+  map.layers()
+    .filter_map(|layer| match layer.layer_type() {
+      LayerType::Objects(obj_layer) => Some(obj_layer.objects().collect::<Vec<_>>()),
+      _ => None,
+    })
+    .flatten()
+    .collect()
+}
+
+/// Renders a property as the Rust expression passed to `ObjectInit`, or says why it can't be.
+fn property_value(property: &Property, value: &PropertyValue, object_ids: &[u32]) -> std::result::Result<String, String> {
+  match (&property.kind, value) {
+    (PropertyKind::Int, PropertyValue::IntValue(value)) => Ok(value.to_string()),
+    (PropertyKind::Bool, PropertyValue::BoolValue(value)) => Ok(value.to_string()),
+    (PropertyKind::String, PropertyValue::StringValue(value)) => Ok(format!("{value:?}")),
+    (PropertyKind::Enum { path, variants }, PropertyValue::StringValue(value)) => {
+      if variants.contains(&value.as_str()) {
+        Ok(format!("{path}::{value}"))
+      } else {
+        Err(format!("is \"{value}\", expected one of {}", variants.join(", ")))
+      }
+    }
+    (PropertyKind::Object, PropertyValue::ObjectValue(0)) => Ok("None".to_string()),
+    (PropertyKind::Object, PropertyValue::ObjectValue(id)) => {
+      match object_ids.iter().position(|object_id| object_id == id) {
+        Some(idx) => Ok(format!("Some({idx})")),
+        None => Err(format!("refers to object {id}, which isn't in this map")),
+      }
+    }
+    (kind, value) => Err(format!("should be {} but is {value:?}", match kind {
+      PropertyKind::Int => "an int",
+      PropertyKind::Bool => "a bool",
+      PropertyKind::String | PropertyKind::Enum { .. } => "a string",
+      PropertyKind::Object => "an object",
+    })),
+  }
+}
+
+fn get_flip_str(tile: &LayerTileData) -> &'static str {
+  match (tile.flip_h, tile.flip_v) {
+    (false, false) => "N",
+    (false, true) => "Y",
+    (true, false) => "X",
+    (true, true) => "XY",
+  }
+}
+
+fn get_tile_id(tile: Option<&LayerTileData>) -> DeserializedFlipTile {
+  if let Some(tile) = tile {
+    DeserializedFlipTile {
+      tile_id: tile.id() + 1,
+      suffix: "",
+      flip: get_flip_str(tile),
+    }
+  } else {
+    DeserializedFlipTile {
+      tile_id: 0,
+      suffix: "",
+      flip: "N",
+    }
+  }
+}
+
+pub fn export_level(config: &LevelConfig, tileset: &TilesetConfig, metatile_count: usize, maps_dir: &str, out_dir: &str, loader: &mut Loader) -> Result<()> {
+  let level = config.name;
+  let full_path = format!("{maps_dir}/{level}.tmx");
+  let map = load_map(loader, &full_path);
+  validate_level(&full_path, &map, tileset, metatile_count);
+
+  let output_file = File::create(format!("{out_dir}/{level}.rs"))?;
+  let mut writer = BufWriter::new(output_file);
+
+  let mut has_background = false;
+  let mut has_foreground = false;
+  for layer in map.layers() {
+    let layer_name = layer.name.clone();
+    match layer.layer_type() {
+      LayerType::Tiles(tile_layer) => {
+        match tile_layer {
+          TileLayer::Finite(layer) => {
+            if layer_name.as_str() == "Collision" {
+              export_rle_layer(&mut writer, out_dir, level, "COLLISION", layer.width(), layer.height(), |xi, yi| {
+                vec![layer.get_tile_data(xi, yi).map_or(0, |tile| get_collide_tile_type(tile.id()))]
+              })?;
+            } else {
+              let const_name = match layer_name.as_str() {
+                "Primary" => "DATA",
+                "Background" => {
+                  has_background = true;
+                  "BACKGROUND_DATA"
+                }
+                "Foreground" => {
+                  has_foreground = true;
+                  "FOREGROUND_DATA"
+                }
+                _ => unreachable!("Tile layer names are validated"),
+              };
+              // Graphics only exist on hardware, collision and objects are also used by headless runs
+              writeln!(&mut writer, r#"#[cfg(feature = "agb")]"#)?;
+              export_rle_layer(&mut writer, out_dir, level, const_name, layer.width(), layer.height(), |xi, yi| {
+                get_metatile_bytes(layer.get_tile_data(xi, yi), tileset.index_width)
+              })?;
+            }
+          }
+          _ => unreachable!("Infinite tile layers are rejected by validation"),
+        }
+      }
+      _ => {}
+    }
+  }
+
+  let objects = map_objects(&map);
+  let object_ids: Vec<u32> = objects.iter().map(|obj| obj.id()).collect();
+  writeln!(&mut writer, "const OBJECTS: &[O] = &[")?;
+  for obj in &objects {
+    writeln!(&mut writer, "  O::{},", object_init(obj, &object_ids, config.origin))?;
+  }
+  writeln!(&mut writer, "];")?;
+  writeln!(&mut writer, r#"
+    pub fn load_objects(world: &mut World) -> Vec<Entity> {{
+      crate::object::build_objects(world, OBJECTS)
+    }}
+  "#)?;
+
+  let (map_w, map_h) = (map.width, map.height);
+  let tileset_module = tileset.module;
+  let palettes = config.palettes.map_or(String::new(), |palettes| format!(".with_palettes({palettes})"));
+  let origin = match config.origin {
+    (0, 0) => String::new(),
+    (x, y) => format!(".with_origin(agb_fixnum::Vector2D::new({x}, {y}))"),
+  };
+  let background_data = if has_background { "Some(BACKGROUND_DATA)" } else { "None" };
+  let foreground_data = if has_foreground { "Some(FOREGROUND_DATA)" } else { "None" };
+  writeln!(
+    &mut writer,
+    r#"
+    use alloc::vec::Vec;
+    use agb_ext::{{
+      rle::RleLayer,
+      collision::CollideTilemap,
+      ecs::Entity,
+    }};
+    #[cfg(feature = "agb")]
+    use agb_ext::tiles::Tilemap;
+    use crate::world::{{World}};
+    use crate::object::{{ObjectInit as O}};
+
+    #[cfg(feature = "agb")]
+    pub static TILEMAP: Tilemap = Tilemap::new(DATA, {background_data}, {foreground_data}, COLLISION, {map_w}, &crate::{tileset_module}::TILESET_DATA){palettes}{origin};
+
+    pub fn collide_tilemap() -> CollideTilemap {{
+      CollideTilemap::new(COLLISION, {map_w}, {map_h}){origin}
+    }}
+
+    "#
+  )?;
+
+  Ok(())
+}
+
+/// Writes a layer as a run-length encoded blob next to the generated source, see `agb_ext::tiles::RleLayer`.
+fn export_rle_layer(
+    writer: &mut impl Write,
+    out_dir: &str,
+    level: &str,
+    const_name: &str,
+    width: u32,
+    height: u32,
+    cell: impl Fn(i32, i32) -> Vec<u8>) -> Result<()> {
+  let mut data = vec![];
+  let mut rows = vec![];
+  let mut value_size = 0;
+  for yi in 0..height as i32 {
+    rows.push(data.len() as u32);
+    let mut xi = 0;
+    while xi < width as i32 {
+      let value = cell(xi, yi);
+      let mut run = 1;
+      while xi + run < width as i32 && run < u8::MAX as i32 && cell(xi + run, yi) == value {
+        run += 1;
+      }
+      value_size = value.len();
+      data.push(run as u8);
+      data.extend(value);
+      xi += run;
+    }
+  }
+
+  let file_name = format!("{level}_{}.rle", const_name.to_lowercase());
+  std::fs::write(format!("{out_dir}/{file_name}"), &data)?;
+  writeln!(
+    writer,
+    r#"const {const_name}: RleLayer = RleLayer::new(include_bytes!(concat!(env!("OUT_DIR"), "/{file_name}")), &{rows:?}, {value_size});"#
+  )
+}
+
+/// Little endian metatile index (1-based, 0 is empty) followed by the bits `FlipTile::from_flip_bits` expects.
+fn get_metatile_bytes(tile: Option<&LayerTileData>, index_width: IndexWidth) -> Vec<u8> {
+  let (id, flip) = if let Some(tile) = tile {
+    let flip = match (tile.flip_h, tile.flip_v) {
+      (false, false) => 0,
+      (true, false) => 1,
+      (false, true) => 2,
+      (true, true) => 3,
+    };
+    (tile.id() + 1, flip)
+  } else {
+    (0, 0)
+  };
+  let mut bytes = index_width.bytes(id);
+  bytes.push(flip);
+  bytes
+}
+
+/// Matches the discriminants of `CollideTileType`, 0 being `Pass`.
+fn get_collide_tile_type(tile: u32) -> u8 {
+  match tile {
+    0..=10 => tile as u8 + 1,
+    _ => 0,
+  }
+}
+
+fn object_init(obj: &tiled::ObjectData, object_ids: &[u32], origin: (i32, i32)) -> String {
+  let object_type = object_type(&obj.user_type).expect("Object types are validated");
+  let mut args = vec![shape_args(obj, origin)];
+  for property in object_type.properties {
+    args.push(match obj.properties.get(property.name) {
+      Some(value) => property_value(property, value, object_ids).expect("Object properties are validated"),
+      None => property.default.expect("Required properties are validated").to_string(),
+    });
+  }
+  format!("{}({})", obj.user_type, args.join(","))
+}
+
+fn shape_args(obj: &tiled::ObjectData, origin: (i32, i32)) -> String {
+  let (x, y) = (obj.x + (origin.0 * 16) as f32, obj.y + (origin.1 * 16) as f32);
+  match &obj.shape {
+    ObjectShape::Rect { width, height } | ObjectShape::Ellipse { width, height } => {
+      format!("{},{},{},{}", coord(x), coord(y), coord(*width), coord(*height))
+    }
+    ObjectShape::Point(..) => format!("{},{}", coord(x), coord(y)),
+    ObjectShape::Polyline { points } | ObjectShape::Polygon { points } => {
+      let points: Vec<String> = points.iter()
+        .map(|(px, py)| format!("({},{})", coord(x + px), coord(y + py)))
+        .collect();
+      let closed = matches!(obj.shape, ObjectShape::Polygon { .. });
+      format!("&[{}],{closed}", points.join(","))
+    }
+    _ => unreachable!("Object shapes are validated"),
+  }
+}
+
+/// Points placed without snapping land between pixels.
+fn coord(value: f32) -> i32 {
+  value.round() as i32
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use tiled::Loader;
+use tiled_export::{export_all, export_level, export_tileset, IndexWidth, LevelConfig, TilesetConfig, LEVELS, TILESETS};
+
+const MAPS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../maps");
+const TEST_MAPS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/maps");
+const SNAPSHOTS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots");
+
+/// Compares against `tests/snapshots/{name}.rs`. Run with `UPDATE_SNAPSHOTS=1` to accept changes.
+fn assert_snapshot(name: &str, generated: &str) {
+  let path = format!("{SNAPSHOTS_DIR}/{name}.rs");
+  if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+    fs::write(&path, generated).unwrap();
+    return;
+  }
+  let expected = fs::read_to_string(&path)
+    .unwrap_or_else(|_| panic!("Missing snapshot {path}, run with UPDATE_SNAPSHOTS=1 to create it"));
+  assert!(generated == expected, "Generated {name}.rs doesn't match {path}:\n{generated}");
+}
+
+fn out_dir(name: &str) -> String {
+  let out_dir = format!("{}/{name}", env!("CARGO_TARGET_TMPDIR"));
+  fs::create_dir_all(&out_dir).unwrap();
+  out_dir
+}
+
+/// Every `.tmx` in `maps/` other than a tileset's metatiles, so a map missing from `LEVELS` fails instead of being
+/// silently left out.
+fn level_maps() -> Vec<String> {
+  let mut names: Vec<String> = fs::read_dir(MAPS_DIR).unwrap()
+    .map(|entry| entry.unwrap().path())
+    .filter(|path| path.extension().is_some_and(|ext| ext == "tmx"))
+    .map(|path| path.file_stem().unwrap().to_string_lossy().into_owned())
+    .filter(|name| !TILESETS.iter().any(|tileset| tileset.metatiles == name))
+    .collect();
+  names.sort();
+  names
+}
+
+#[test]
+fn levels_match_snapshots() {
+  let out_dir = out_dir("levels");
+  export_all(MAPS_DIR, &out_dir).unwrap();
+  for name in level_maps() {
+    assert!(LEVELS.iter().any(|level| level.name == name), "maps/{name}.tmx has no LevelConfig in LEVELS");
+    assert_snapshot(&name, &fs::read_to_string(format!("{out_dir}/{name}.rs")).unwrap());
+  }
+}
+
+#[test]
+fn multiple_and_empty_object_layers_share_one_objects_array() {
+  let out_dir = out_dir("object_layers");
+  let mut loader = Loader::new();
+  let metatile_count = export_tileset(&TILESETS[0], MAPS_DIR, &out_dir, &mut loader).unwrap();
+  let level = LevelConfig { name: "object_layers", tileset: TILESETS[0].module, palettes: None, origin: (0, 0) };
+  export_level(&level, &TILESETS[0], metatile_count, TEST_MAPS_DIR, &out_dir, &mut loader).unwrap();
+
+  let generated = fs::read_to_string(format!("{out_dir}/object_layers.rs")).unwrap();
+  assert_eq!(generated.matches("const OBJECTS").count(), 1);
+  assert_eq!(generated.matches("pub fn load_objects").count(), 1);
+  assert_snapshot("object_layers", &generated);
+}
+
+#[test]
+fn origin_places_tilemaps_and_objects_in_the_world() {
+  let out_dir = out_dir("origin");
+  let mut loader = Loader::new();
+  let metatile_count = export_tileset(&TILESETS[0], MAPS_DIR, &out_dir, &mut loader).unwrap();
+  let level = LevelConfig { name: "object_layers", tileset: TILESETS[0].module, palettes: None, origin: (4, -1) };
+  export_level(&level, &TILESETS[0], metatile_count, TEST_MAPS_DIR, &out_dir, &mut loader).unwrap();
+
+  let generated = fs::read_to_string(format!("{out_dir}/object_layers.rs")).unwrap();
+  assert_eq!(generated.matches(".with_origin(agb_fixnum::Vector2D::new(4, -1))").count(), 2);
+  assert!(generated.contains("O::PlayerStart(72,16),"));
+  assert!(generated.contains("O::Path(&[(80,-8),(112,-8),(112,9)],false),"));
+}
+
+#[test]
+fn u16_indices_add_a_high_byte() {
+  let export = |name: &str, index_width| {
+    let out_dir = out_dir(name);
+    let mut loader = Loader::new();
+    let tileset = TilesetConfig { index_width, ..TILESETS[0] };
+    let metatile_count = export_tileset(&tileset, MAPS_DIR, &out_dir, &mut loader).unwrap();
+    let level = LevelConfig { name: "object_layers", tileset: tileset.module, palettes: None, origin: (0, 0) };
+    export_level(&level, &tileset, metatile_count, TEST_MAPS_DIR, &out_dir, &mut loader).unwrap();
+    (fs::read_to_string(format!("{out_dir}/object_layers.rs")).unwrap(), fs::read(format!("{out_dir}/object_layers_data.rle")).unwrap())
+  };
+  let (u8_source, u8_data) = export("index_u8", IndexWidth::U8);
+  let (u16_source, u16_data) = export("index_u16", IndexWidth::U16);
+
+  let data_line = |source: &str| source.lines().find(|line| line.starts_with("const DATA")).unwrap().to_string();
+  assert!(data_line(&u8_source).ends_with(", 2);"));
+  assert!(data_line(&u16_source).ends_with(", 3);"));
+  // Each run is its length, then the index low byte first, then the flip bits
+  let widened: Vec<u8> = u8_data.chunks(3).flat_map(|run| [run[0], run[1], 0, run[2]]).collect();
+  assert_eq!(u16_data, widened);
+}
+
+#[test]
+#[should_panic(expected = "metatileset \"metatileset\" has no metatiles")]
+fn empty_metatilesets_are_reported() {
+  let out_dir = out_dir("empty_metatileset");
+  let level = LevelConfig { name: "object_layers", tileset: TILESETS[0].module, palettes: None, origin: (0, 0) };
+  export_level(&level, &TILESETS[0], 0, TEST_MAPS_DIR, &out_dir, &mut Loader::new()).unwrap();
+}
@@ -0,0 +1,34 @@
+#[cfg(feature = "agb")]
+const DATA: RleLayer = RleLayer::new(include_bytes!(concat!(env!("OUT_DIR"), "/grambles_room_data.rle")), &[0, 18, 42, 57, 78, 99, 123, 150, 195, 216], 2);
+#[cfg(feature = "agb")]
+const FOREGROUND_DATA: RleLayer = RleLayer::new(include_bytes!(concat!(env!("OUT_DIR"), "/grambles_room_foreground_data.rle")), &[0, 12, 24, 27, 42, 51, 60, 69, 87, 90], 2);
+const COLLISION: RleLayer = RleLayer::new(include_bytes!(concat!(env!("OUT_DIR"), "/grambles_room_collision.rle")), &[0, 12, 28, 38, 50, 64, 78, 92, 114, 124], 1);
+const OBJECTS: &[O] = &[
+  O::ForegroundHide(176,120,32,8),
+  O::PlayerStart(48,96),
+];
+
+    pub fn load_objects(world: &mut World) -> Vec<Entity> {
+      crate::object::build_objects(world, OBJECTS)
+    }
+  
+
+    use alloc::vec::Vec;
+    use agb_ext::{
+      rle::RleLayer,
+      collision::CollideTilemap,
+      ecs::Entity,
+    };
+    #[cfg(feature = "agb")]
+    use agb_ext::tiles::Tilemap;
+    use crate::world::{World};
+    use crate::object::{ObjectInit as O};
+
+    #[cfg(feature = "agb")]
+    pub static TILEMAP: Tilemap = Tilemap::new(DATA, None, Some(FOREGROUND_DATA), COLLISION, 30, &crate::tileset::TILESET_DATA);
+
+    pub fn collide_tilemap() -> CollideTilemap {
+      CollideTilemap::new(COLLISION, 30, 10)
+    }
+
+    
@@ -0,0 +1,39 @@
+#[cfg(feature = "agb")]
+const DATA: RleLayer = RleLayer::new(include_bytes!(concat!(env!("OUT_DIR"), "/object_layers_data.rle")), &[0, 3, 6], 2);
+const COLLISION: RleLayer = RleLayer::new(include_bytes!(concat!(env!("OUT_DIR"), "/object_layers_collision.rle")), &[0, 2, 4], 1);
+const OBJECTS: &[O] = &[
+  O::Checkpoint(0,0,16,32),
+  O::Hazard(32,24,16,8,2),
+  O::Breakable(16,0,16,32,false),
+  O::Switch(0,16,16,16,Some(2)),
+  O::CameraBounds(0,0,64,48),
+  O::PlayerStart(8,32),
+  O::Path(&[(16,8),(48,8),(48,25)],false),
+  O::Hazard(0,32,16,16,1),
+  O::AbilityPickup(48,16,16,16,crate::ability::Ability::DoubleJump),
+];
+
+    pub fn load_objects(world: &mut World) -> Vec<Entity> {
+      crate::object::build_objects(world, OBJECTS)
+    }
+  
+
+    use alloc::vec::Vec;
+    use agb_ext::{
+      rle::RleLayer,
+      collision::CollideTilemap,
+      ecs::Entity,
+    };
+    #[cfg(feature = "agb")]
+    use agb_ext::tiles::Tilemap;
+    use crate::world::{World};
+    use crate::object::{ObjectInit as O};
+
+    #[cfg(feature = "agb")]
+    pub static TILEMAP: Tilemap = Tilemap::new(DATA, None, None, COLLISION, 4, &crate::tileset::TILESET_DATA);
+
+    pub fn collide_tilemap() -> CollideTilemap {
+      CollideTilemap::new(COLLISION, 4, 3)
+    }
+
+    
@@ -0,0 +1,32 @@
+#[cfg(feature = "agb")]
+const DATA: RleLayer = RleLayer::new(include_bytes!(concat!(env!("OUT_DIR"), "/slope_test_data.rle")), &[0, 3, 6, 9, 12, 15, 18, 21, 24, 27, 30, 33, 36, 39, 42, 45, 48, 51, 54, 57], 2);
+#[cfg(feature = "agb")]
+const FOREGROUND_DATA: RleLayer = RleLayer::new(include_bytes!(concat!(env!("OUT_DIR"), "/slope_test_foreground_data.rle")), &[0, 9, 18, 27, 42, 63, 84, 108, 135, 153, 168, 180, 192, 213, 222, 237, 252, 279, 282, 285], 2);
+const COLLISION: RleLayer = RleLayer::new(include_bytes!(concat!(env!("OUT_DIR"), "/slope_test_collision.rle")), &[0, 6, 12, 18, 24, 38, 52, 68, 84, 96, 104, 112, 120, 128, 134, 144, 154, 174, 176, 178], 1);
+const OBJECTS: &[O] = &[
+];
+
+    pub fn load_objects(world: &mut World) -> Vec<Entity> {
+      crate::object::build_objects(world, OBJECTS)
+    }
+  
+
+    use alloc::vec::Vec;
+    use agb_ext::{
+      rle::RleLayer,
+      collision::CollideTilemap,
+      ecs::Entity,
+    };
+    #[cfg(feature = "agb")]
+    use agb_ext::tiles::Tilemap;
+    use crate::world::{World};
+    use crate::object::{ObjectInit as O};
+
+    #[cfg(feature = "agb")]
+    pub static TILEMAP: Tilemap = Tilemap::new(DATA, None, Some(FOREGROUND_DATA), COLLISION, 15, &crate::tileset::TILESET_DATA);
+
+    pub fn collide_tilemap() -> CollideTilemap {
+      CollideTilemap::new(COLLISION, 15, 20)
+    }
+
+    